@@ -0,0 +1,18 @@
+-- A conversation with one contact reachable by phone (handle 36) and email
+-- (handle 37), plus an unrelated contact (handle 40). Dates are nanoseconds
+-- since 2001-01-01: 757382400000000000 is 2025-01-01T00:00:00Z.
+INSERT INTO handle (ROWID, id, country, service) VALUES
+  (36, '+61400000000', 'au', 'iMessage'),
+  (37, 'me@example.com', 'au', 'iMessage'),
+  (40, '+61499999999', 'au', 'iMessage');
+INSERT INTO message (ROWID, guid, text, handle_id, service, date, is_from_me, is_sent, is_delivered, is_finished) VALUES
+  (100, 'A1F0C1E2-0000-4000-8000-000000000100', '🤖 [harold:0.1] Fixed the race. (main)', 36, 'iMessage', 757382400000000000, 1, 1, 1, 1),
+  (101, 'A1F0C1E2-0000-4000-8000-000000000101', '[harold] ship it', 36, 'iMessage', 757382460000000000, 0, 0, 1, 1),
+  (102, 'A1F0C1E2-0000-4000-8000-000000000102', 'dinner at 7?', 40, 'iMessage', 757382470000000000, 0, 0, 1, 1),
+  (103, 'A1F0C1E2-0000-4000-8000-000000000103', NULL, 36, 'iMessage', 757382480000000000, 0, 0, 1, 1),
+  (104, 'A1F0C1E2-0000-4000-8000-000000000104', 'run the tests first', 37, 'iMessage', 757382490000000000, 1, 1, 1, 1),
+  (105, 'A1F0C1E2-0000-4000-8000-000000000105', '', 36, 'iMessage', 757382500000000000, 0, 0, 1, 1),
+  (106, 'A1F0C1E2-0000-4000-8000-000000000106', 'and push', 36, 'iMessage', 757382510000000000, 0, 0, 1, 1);
+-- Newer devices leave text NULL and keep the content in the attributedBody
+-- typedstream; this is attributed_body_short.bin.
+UPDATE message SET attributedBody = X'040B73747265616D747970656481E803840140848484124E5341747472696275746564537472696E67008484084E534F626A656374008592848484084E53537472696E67019484012B1E5B6861726F6C645D20616C736F2062756D70207468652076657273696F6E8684026949011E928484840C4E5344696374696F6E617279009484016901928496961D5F5F6B494D4D657373616765506172744174747269627574654E616D658692848484084E534E756D626572008484074E5356616C7565009484012A84999900868686' WHERE ROWID = 103;
@@ -0,0 +1,304 @@
+pub mod sent;
+mod typedstream;
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params};
+use tracing::{info, warn};
+
+use crate::settings::get_settings;
+use crate::store::ImessageSent;
+
+// ---------------------------------------------------------------------------
+// Read-only access to the Messages database
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChatDbError {
+    #[error("cannot open {path}: {source}")]
+    Open {
+        path: String,
+        #[source]
+        source: rusqlite::Error,
+    },
+    #[error("chat.db query failed: {0}")]
+    Query(#[from] rusqlite::Error),
+}
+
+/// A message row: `ROWID` and `text`.
+pub type Message = (i64, String);
+
+/// The text of a row: `text` when set, otherwise the string decoded from
+/// `attributedBody`, which newer devices write instead. Empty when neither
+/// holds any.
+fn message_text(rowid: i64, text: Option<String>, attributed_body: Option<Vec<u8>>) -> String {
+    if let Some(text) = text.filter(|t| !t.is_empty()) {
+        return text;
+    }
+    let text = attributed_body.and_then(|body| typedstream::decode(&body));
+    if text.is_none() {
+        warn!(rowid, "message has no text and no decodable attributedBody");
+    }
+    text.unwrap_or_default()
+}
+
+/// A read-only connection to chat.db. Every query is parameterised; values are
+/// never interpolated into SQL.
+pub struct Reader {
+    conn: Connection,
+}
+
+impl Reader {
+    /// Open `path` read-only. Lock waits are bounded by `busy_timeout`.
+    pub fn open(path: &str, busy_timeout: Duration) -> Result<Self, ChatDbError> {
+        let open_err = |source| ChatDbError::Open {
+            path: path.to_string(),
+            source,
+        };
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(open_err)?;
+        conn.busy_timeout(busy_timeout).map_err(open_err)?;
+        conn.pragma_update(None, "query_only", true)
+            .map_err(open_err)?;
+        Ok(Self { conn })
+    }
+
+    /// Highest `message.ROWID`, or 0 for an empty table.
+    pub fn max_rowid(&self) -> Result<i64, ChatDbError> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT COALESCE(MAX(ROWID), 0) FROM message")?;
+        Ok(stmt.query_row([], |row| row.get(0))?)
+    }
+
+    /// Highest rowid of a message dated before `apple_nanos` (nanoseconds
+    /// since 2001-01-01), or 0.
+    pub fn last_rowid_before(&self, apple_nanos: i64) -> Result<i64, ChatDbError> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT COALESCE(MAX(ROWID), 0) FROM message WHERE date < ?1")?;
+        Ok(stmt.query_row(params![apple_nanos], |row| row.get(0))?)
+    }
+
+    /// Messages with text or an `attributedBody` after `after_rowid` with any of `handle_ids`,
+    /// sent by the user's other devices (`from_me`) or received, oldest first.
+    pub fn messages_after(
+        &self,
+        after_rowid: i64,
+        handle_ids: &[i64],
+        from_me: bool,
+    ) -> Result<Vec<Message>, ChatDbError> {
+        if handle_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let ids = serde_json::to_string(handle_ids).unwrap_or_default();
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT ROWID, text, attributedBody FROM message \
+             WHERE ROWID > ?1 \
+               AND handle_id IN (SELECT value FROM json_each(?2)) \
+               AND is_from_me = ?3 \
+               AND (length(text) > 0 OR attributedBody IS NOT NULL) \
+             ORDER BY ROWID ASC",
+        )?;
+        let rows = stmt.query_map(params![after_rowid, ids, from_me], |row| {
+            let rowid = row.get(0)?;
+            Ok((rowid, message_text(rowid, row.get(1)?, row.get(2)?)))
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// The first message sent after `after_rowid` to any of `handle_ids` whose
+    /// text is `text`, skipping rows `claimed` already.
+    pub fn find_sent(
+        &self,
+        after_rowid: i64,
+        handle_ids: &[i64],
+        text: &str,
+        claimed: impl Fn(i64) -> bool,
+    ) -> Result<Option<ImessageSent>, ChatDbError> {
+        let ids = serde_json::to_string(handle_ids).unwrap_or_default();
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT ROWID, guid, text, attributedBody FROM message \
+             WHERE ROWID > ?1 \
+               AND handle_id IN (SELECT value FROM json_each(?2)) \
+               AND is_from_me = 1 \
+             ORDER BY ROWID ASC",
+        )?;
+        let mut rows = stmt.query(params![after_rowid, ids])?;
+        while let Some(row) = rows.next()? {
+            let rowid = row.get(0)?;
+            if !claimed(rowid)
+                && message_text(rowid, row.get(2)?, row.get(3)?).trim() == text.trim()
+            {
+                return Ok(Some(ImessageSent {
+                    rowid,
+                    guid: row.get(1)?,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Text of the newest message sent to `handle_id`.
+    pub fn last_outgoing_text(&self, handle_id: i64) -> Result<Option<String>, ChatDbError> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT ROWID, text, attributedBody FROM message \
+             WHERE handle_id = ?1 AND is_from_me = 1 \
+             ORDER BY ROWID DESC LIMIT 1",
+        )?;
+        let text = stmt
+            .query_row(params![handle_id], |row| {
+                Ok(message_text(row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .optional()?;
+        Ok(text.filter(|t| !t.trim().is_empty()))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Shared connection
+// ---------------------------------------------------------------------------
+
+/// One long-lived connection for the listener and the duplicate check. Opened
+/// on first use and dropped after an error, so a replaced database is reopened.
+static READER: Mutex<Option<Reader>> = Mutex::new(None);
+
+/// Run `f` against the shared reader. Blocking — call from `spawn_blocking`.
+pub fn with_reader<T>(f: impl FnOnce(&Reader) -> Result<T, ChatDbError>) -> Result<T, ChatDbError> {
+    let mut guard = READER.lock().unwrap_or_else(|e| e.into_inner());
+    let reader = match guard.take() {
+        Some(reader) => reader,
+        None => {
+            let cfg = get_settings();
+            let path = cfg.chat_db.resolved_path();
+            let reader = Reader::open(&path, Duration::from_secs(cfg.timeouts.sqlite_secs))?;
+            info!(path, "chat.db opened read-only");
+            reader
+        }
+    };
+    let result = f(&reader);
+    match &result {
+        Ok(_) => *guard = Some(reader),
+        Err(e) => warn!(error = %e, "chat.db read failed; reopening on next use"),
+    }
+    result
+}
+
+// ---------------------------------------------------------------------------
+// Tests — against a fixture database with the macOS schema
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Build a chat.db from the schema and message fixtures; returns its path.
+    pub(crate) fn fixture_db() -> String {
+        let path = std::env::temp_dir().join(format!("harold-chat-{}.db", uuid::Uuid::new_v4()));
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(include_str!("fixtures/schema.sql"))
+            .unwrap();
+        conn.execute_batch(include_str!("fixtures/messages.sql"))
+            .unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn open(path: &str) -> Reader {
+        Reader::open(path, Duration::from_secs(1)).unwrap()
+    }
+
+    #[test]
+    fn messages_after_filters_by_handle_direction_and_rowid() {
+        let path = fixture_db();
+        let reader = open(&path);
+
+        let inbound = reader.messages_after(0, &[36, 37], false).unwrap();
+        assert_eq!(
+            inbound,
+            vec![
+                (101, "[harold] ship it".into()),
+                (103, "[harold] also bump the version".into()),
+                (106, "and push".into()),
+            ]
+        );
+        let sent = reader.messages_after(100, &[36, 37], true).unwrap();
+        assert_eq!(sent, vec![(104, "run the tests first".into())]);
+        assert!(reader.messages_after(106, &[36], false).unwrap().is_empty());
+        assert!(reader.messages_after(0, &[], false).unwrap().is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn rowid_queries_and_last_outgoing_text() {
+        let path = fixture_db();
+        let reader = open(&path);
+
+        assert_eq!(reader.max_rowid().unwrap(), 106);
+        // 2025-01-01T00:01:05Z: after rowids 100 and 101.
+        assert_eq!(
+            reader.last_rowid_before(757_382_465_000_000_000).unwrap(),
+            101
+        );
+        assert_eq!(reader.last_rowid_before(0).unwrap(), 0);
+        assert_eq!(
+            reader.last_outgoing_text(36).unwrap().as_deref(),
+            Some("🤖 [harold:0.1] Fixed the race. (main)")
+        );
+        assert_eq!(reader.last_outgoing_text(40).unwrap(), None);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn find_sent_matches_text_after_the_watermark() {
+        let path = fixture_db();
+        let reader = open(&path);
+        let notification = "🤖 [harold:0.1] Fixed the race. (main)";
+
+        let sent = reader
+            .find_sent(99, &[36, 37], notification, |_| false)
+            .unwrap();
+        assert_eq!(
+            sent,
+            Some(ImessageSent {
+                rowid: 100,
+                guid: "A1F0C1E2-0000-4000-8000-000000000100".into(),
+            })
+        );
+        assert_eq!(
+            reader
+                .find_sent(100, &[36, 37], notification, |_| false)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            reader
+                .find_sent(99, &[36, 37], notification, |r| r == 100)
+                .unwrap(),
+            None
+        );
+        // Received rows are never Harold's.
+        assert_eq!(
+            reader
+                .find_sent(0, &[36], "[harold] ship it", |_| false)
+                .unwrap(),
+            None
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn reader_is_read_only_and_reports_typed_errors() {
+        let path = fixture_db();
+        let reader = open(&path);
+        let err = reader.conn.execute("DELETE FROM message", []).unwrap_err();
+        assert!(err.to_string().contains("readonly") || err.to_string().contains("read"));
+        let _ = std::fs::remove_file(path);
+
+        let missing = Reader::open("/nonexistent/chat.db", Duration::from_secs(1));
+        assert!(matches!(missing, Err(ChatDbError::Open { .. })));
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use super::with_reader;
+use crate::settings::get_settings;
+use crate::state;
+use crate::store::ImessageSent;
+
+// ---------------------------------------------------------------------------
+// Harold's own iMessages — so they never come back in as replies
+// ---------------------------------------------------------------------------
+
+/// Every notification Harold sends starts with this.
+pub const NOTIFICATION_PREFIX: &str = "🤖";
+
+/// Messages can take a moment to write a sent message to chat.db.
+const LOOKUP_ATTEMPTS: u32 = 10;
+const LOOKUP_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Held for a whole send, and by the iMessage source while it reads, so the
+/// source never sees a sent row before it is claimed.
+static SENDING: Mutex<()> = Mutex::new(());
+
+/// Claimed rows not yet recorded as `ImessageSent` events.
+static OUTBOX: Mutex<Vec<ImessageSent>> = Mutex::new(Vec::new());
+
+/// Texts of recent sends whose rows could not be claimed, newest last.
+static UNCLAIMED: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+const UNCLAIMED_KEPT: usize = 20;
+
+pub fn sending() -> MutexGuard<'static, ()> {
+    SENDING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn outbox() -> MutexGuard<'static, Vec<ImessageSent>> {
+    OUTBOX.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether `rowid` is a message Harold sent.
+pub fn is_own(rowid: i64) -> bool {
+    state::is_sent_imessage(rowid) || outbox().iter().any(|sent| sent.rowid == rowid)
+}
+
+/// Whether the self-sent row `rowid` with `text` is Harold's own: claimed, or,
+/// in case its claim failed, exactly the text of an unclaimed send.
+pub fn is_own_message(rowid: i64, text: &str) -> bool {
+    is_own(rowid) || looks_own(text)
+}
+
+fn looks_own(text: &str) -> bool {
+    let text = text.trim();
+    UNCLAIMED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .any(|sent| sent == text)
+}
+
+/// Remember `text` as sent but not claimed, so its row is still skipped.
+pub fn unclaimed(text: &str) {
+    let mut unclaimed = UNCLAIMED.lock().unwrap_or_else(|e| e.into_inner());
+    unclaimed.push_back(text.trim().to_string());
+    if unclaimed.len() > UNCLAIMED_KEPT {
+        unclaimed.pop_front();
+    }
+}
+
+/// Find the row for `text`, just sent, among messages after `after_rowid` and
+/// claim it, or remember it as [`unclaimed`]. Blocking — call while holding
+/// [`sending`].
+pub fn claim(after_rowid: i64, text: &str) {
+    let handle_ids = &get_settings().imessage.handle_ids;
+    for attempt in 0..LOOKUP_ATTEMPTS {
+        if attempt > 0 {
+            std::thread::sleep(LOOKUP_INTERVAL);
+        }
+        match with_reader(|reader| reader.find_sent(after_rowid, handle_ids, text, is_own)) {
+            Ok(Some(sent)) => {
+                info!(rowid = sent.rowid, guid = %sent.guid, "sent iMessage claimed");
+                outbox().push(sent);
+                return;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!(error = %e, "sent iMessage lookup failed");
+                unclaimed(text);
+                return;
+            }
+        }
+    }
+    warn!("sent iMessage not found in chat.db; skipping it by text instead");
+    unclaimed(text);
+}
+
+/// Claimed rows still to be recorded, oldest first.
+pub fn unrecorded() -> Vec<ImessageSent> {
+    outbox().clone()
+}
+
+/// `rowid` is now in the event store.
+pub fn recorded(rowid: i64) {
+    outbox().retain(|sent| sent.rowid != rowid);
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_unclaimed_sends_look_own() {
+        // A reply typed elsewhere is not Harold's just because of the prefix.
+        assert!(!looks_own("🤖 [harold:0.1] Done (main)"));
+        assert!(!looks_own("run the tests first"));
+
+        unclaimed("🤖 [harold:0.1] Done (main)");
+        assert!(looks_own("🤖 [harold:0.1] Done (main)"));
+
+        unclaimed("✓ Delivered to [api:0.0] ");
+        assert!(looks_own("✓ Delivered to [api:0.0]"));
+        for n in 0..UNCLAIMED_KEPT {
+            unclaimed(&format!("filler {n}"));
+        }
+        // Only the most recent unclaimed sends are kept.
+        assert!(!looks_own("✓ Delivered to [api:0.0]"));
+    }
+}
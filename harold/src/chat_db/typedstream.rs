@@ -0,0 +1,91 @@
+// ---------------------------------------------------------------------------
+// attributedBody — the NSAttributedString archive behind a message
+// ---------------------------------------------------------------------------
+//
+// Messages archives `message.attributedBody` with NSArchiver's typedstream
+// format. Only the plain string is needed: it is the first C string (`+`)
+// encoded after the NSString / NSMutableString class, prefixed by its byte
+// length. Attribute runs, mentions and attachments that follow are ignored.
+
+const HEADER: &[u8] = b"\x04\x0bstreamtyped";
+const STRING_CLASSES: [&[u8]; 2] = [b"\x08NSString", b"\x0fNSMutableString"];
+/// New-object tag, then the one-byte type encoding `+` (a C string).
+const C_STRING: &[u8] = b"\x84\x01+";
+
+/// Integer tags: a 2- or 4-byte little-endian value follows.
+const TAG_I16: u8 = 0x81;
+const TAG_I32: u8 = 0x82;
+
+/// The message text inside an `attributedBody` blob, or `None` if the blob is
+/// not a typedstream string archive.
+pub fn decode(blob: &[u8]) -> Option<String> {
+    if !blob.starts_with(HEADER) {
+        return None;
+    }
+    let class_end = STRING_CLASSES
+        .iter()
+        .filter_map(|class| find(blob, class).map(|at| at + class.len()))
+        .min()?;
+    let rest = &blob[class_end..];
+    let rest = &rest[find(rest, C_STRING)? + C_STRING.len()..];
+    let (len, rest) = read_length(rest)?;
+    let text = std::str::from_utf8(rest.get(..len)?).ok()?;
+    Some(text.to_string())
+}
+
+/// A typedstream length: one byte below 0x80, otherwise a tagged wider integer.
+fn read_length(bytes: &[u8]) -> Option<(usize, &[u8])> {
+    let (&tag, rest) = bytes.split_first()?;
+    match tag {
+        TAG_I16 => {
+            let (n, rest) = rest.split_first_chunk::<2>()?;
+            Some((usize::from(u16::from_le_bytes(*n)), rest))
+        }
+        TAG_I32 => {
+            let (n, rest) = rest.split_first_chunk::<4>()?;
+            Some((usize::try_from(u32::from_le_bytes(*n)).ok()?, rest))
+        }
+        n if n < 0x80 => Some((usize::from(n), rest)),
+        _ => None,
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHORT: &[u8] = include_bytes!("fixtures/attributed_body_short.bin");
+    const LONG: &[u8] = include_bytes!("fixtures/attributed_body_long.bin");
+
+    #[test]
+    fn decodes_short_and_long_strings() {
+        assert_eq!(
+            decode(SHORT).as_deref(),
+            Some("[harold] also bump the version")
+        );
+        // NSMutableString with a 2-byte length and multi-byte characters.
+        let long = decode(LONG).unwrap();
+        assert!(long.starts_with("[api] 🚀 the deploy looks good — before you merge"));
+        assert!(long.ends_with("the new retry limits"));
+        assert_eq!(long.len(), 179);
+    }
+
+    #[test]
+    fn rejects_truncated_or_foreign_blobs() {
+        assert_eq!(decode(b""), None);
+        assert_eq!(decode(b"bplist00\x01\x02"), None);
+        // Cut inside the text: the declared length runs past the end.
+        let cut = SHORT.iter().position(|&b| b == b'[').unwrap() + 4;
+        assert_eq!(decode(&SHORT[..cut]), None);
+        // Header only, no string class.
+        assert_eq!(decode(HEADER), None);
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use events::{EventEnvelope, EventStore};
+use tokio::sync::{mpsc, watch};
+use tonic::Status;
+use tracing::{info, warn};
+
+use crate::harold::HaroldEvent;
+use crate::store::read_events;
+use crate::util::unix_ms;
+
+const PAGE_SIZE: usize = 256;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub type EventSender = mpsc::Sender<Result<HaroldEvent, Status>>;
+
+fn to_proto(e: &EventEnvelope) -> HaroldEvent {
+    HaroldEvent {
+        id: e.id.to_string(),
+        version: e.version,
+        event_type: e.r#type.clone(),
+        payload_json: e.payload.to_string(),
+        timestamp_ms: unix_ms(e.timestamp),
+    }
+}
+
+/// Tail `harold.events` from `from_version`, forwarding events whose type is in
+/// `event_types` (all types when empty). Replays history first, then polls for live
+/// appends until the subscriber disconnects or Harold shuts down.
+pub async fn tail_events(
+    store: Arc<EventStore>,
+    from_version: u64,
+    event_types: HashSet<String>,
+    tx: EventSender,
+    mut shutdown: watch::Receiver<()>,
+) {
+    let mut next = from_version;
+    info!(from_version, types = ?event_types, "event feed subscriber attached");
+
+    loop {
+        let page = match read_events(&store, next, PAGE_SIZE).await {
+            Ok(page) => page,
+            Err(e) => {
+                warn!(error = %e, "event feed: read failed");
+                let _ = tx
+                    .send(Err(Status::internal("event store read failed")))
+                    .await;
+                return;
+            }
+        };
+
+        for event in &page {
+            next = event.version + 1;
+            if !event_types.is_empty() && !event_types.contains(&event.r#type) {
+                continue;
+            }
+            if tx.send(Ok(to_proto(event))).await.is_err() {
+                info!("event feed subscriber detached");
+                return;
+            }
+        }
+
+        // A full page means there is more history to replay — read again immediately.
+        if page.len() == PAGE_SIZE {
+            continue;
+        }
+
+        tokio::select! {
+            _ = shutdown.changed() => {
+                info!("event feed shutting down");
+                return;
+            }
+            () = tx.closed() => {
+                info!("event feed subscriber detached");
+                return;
+            }
+            () = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use time::OffsetDateTime;
+
+use super::directory::{AgentAddress, AgentDirectory};
+use super::{RouteError, handle_label, resolve_pane_arg};
+use crate::presence;
+use crate::state;
+use crate::store::{ControlKey, ControlKeyRequested, Presence};
+
+// ---------------------------------------------------------------------------
+// Reply commands — `!list`, `!status`, `!peek`, control keys, `!help`
+// ---------------------------------------------------------------------------
+
+const PEEK_DEFAULT_LINES: usize = 15;
+const PEEK_MAX_LINES: usize = 60;
+
+/// How long a control key request can be confirmed with `!yes`.
+const CONFIRM_WINDOW: time::Duration = time::Duration::seconds(60);
+
+const HELP: &str = "\
+!list — live panes and how long each has been idle
+!status — presence and panes waiting on you
+!peek <pane> [n] — last n lines of a pane (default 15)
+!stop <pane> — press Escape in a pane
+!interrupt <pane> — press Ctrl-C in a pane
+!enter <pane> — press Enter in a pane
+!yes / !no — confirm or cancel the last key request
+!help — this list
+Anything else is relayed to an agent, e.g. [pane] message.";
+
+/// A reply that asks Harold something instead of being relayed to an agent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    List,
+    Status,
+    Peek {
+        pane: String,
+        lines: usize,
+    },
+    /// Ask to send `key` to `pane`; nothing is sent until `!yes`.
+    Control {
+        key: ControlKey,
+        pane: String,
+    },
+    Confirm,
+    Cancel,
+    Help,
+}
+
+/// A pane and the control key meant for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlTarget {
+    pub pane_id: String,
+    pub label: String,
+    pub key: ControlKey,
+}
+
+/// A control key step for the caller to record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlStep {
+    Requested(ControlTarget),
+    Sent(ControlTarget),
+    Cancelled,
+}
+
+/// What a command sends back, and the control key step it took, if any.
+#[derive(Debug)]
+pub struct Answer {
+    pub text: String,
+    pub control: Option<ControlStep>,
+}
+
+impl From<String> for Answer {
+    fn from(text: String) -> Self {
+        Answer {
+            text,
+            control: None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CommandError {
+    #[error("Unknown command !{0}. Send !help for the list.")]
+    Unknown(String),
+    #[error("Usage: {0}")]
+    Usage(&'static str),
+    #[error(transparent)]
+    Route(#[from] RouteError),
+    #[error("Could not read pane {0}.")]
+    Capture(String),
+    #[error("Nothing to confirm.")]
+    NothingPending,
+    #[error("The request to press {key} in [{label}] expired. Send it again.")]
+    Expired { key: &'static str, label: String },
+    #[error("Could not press {key} in [{label}].")]
+    KeyFailed { key: &'static str, label: String },
+}
+
+impl Command {
+    /// Parse a reply starting with `!`. `None` for anything else, which is
+    /// routed to an agent as before.
+    pub fn parse(text: &str) -> Option<Result<Command, CommandError>> {
+        let mut words = text.trim().strip_prefix('!')?.split_whitespace();
+        let name = words.next().unwrap_or_default().to_lowercase();
+        let args: Vec<&str> = words.collect();
+        Some(match (name.as_str(), args.as_slice()) {
+            ("list", _) => Ok(Command::List),
+            ("status", _) => Ok(Command::Status),
+            ("help", _) => Ok(Command::Help),
+            ("peek", [pane]) => Ok(Command::Peek {
+                pane: pane.to_string(),
+                lines: PEEK_DEFAULT_LINES,
+            }),
+            ("peek", [pane, n]) => match n.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(Command::Peek {
+                    pane: pane.to_string(),
+                    lines: n.min(PEEK_MAX_LINES),
+                }),
+                _ => Err(CommandError::Usage("!peek <pane> [n]")),
+            },
+            ("peek", _) => Err(CommandError::Usage("!peek <pane> [n]")),
+            ("stop" | "interrupt" | "enter", [pane]) => Ok(Command::Control {
+                key: match name.as_str() {
+                    "stop" => ControlKey::Stop,
+                    "interrupt" => ControlKey::Interrupt,
+                    _ => ControlKey::Enter,
+                },
+                pane: pane.to_string(),
+            }),
+            ("stop" | "interrupt" | "enter", _) => {
+                Err(CommandError::Usage("!stop, !interrupt or !enter <pane>"))
+            }
+            ("yes", _) => Ok(Command::Confirm),
+            ("no", _) => Ok(Command::Cancel),
+            _ => Err(CommandError::Unknown(name)),
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::List => "list",
+            Command::Status => "status",
+            Command::Peek { .. } => "peek",
+            Command::Control {
+                key: ControlKey::Stop,
+                ..
+            } => "stop",
+            Command::Control {
+                key: ControlKey::Interrupt,
+                ..
+            } => "interrupt",
+            Command::Control {
+                key: ControlKey::Enter,
+                ..
+            } => "enter",
+            Command::Confirm => "yes",
+            Command::Cancel => "no",
+            Command::Help => "help",
+        }
+    }
+
+    /// Run the command. Blocking.
+    pub fn run(&self) -> Result<Answer, CommandError> {
+        match self {
+            Command::List => list().map(Answer::from),
+            Command::Status => Ok(status().into()),
+            Command::Peek { pane, lines } => peek(pane, *lines).map(Answer::from),
+            Command::Control { key, pane } => request_control(*key, pane),
+            Command::Confirm => confirm_control(OffsetDateTime::now_utc()),
+            Command::Cancel => cancel_control(),
+            Command::Help => Ok(HELP.to_string().into()),
+        }
+    }
+}
+
+fn live_panes() -> Result<Vec<AgentAddress>, CommandError> {
+    let panes = AgentDirectory::TmuxProcessScan.discover();
+    if panes.is_empty() {
+        return Err(RouteError::NoAgents.into());
+    }
+    Ok(panes)
+}
+
+fn list() -> Result<String, CommandError> {
+    let now = OffsetDateTime::now_utc();
+    let lines: Vec<String> = live_panes()?
+        .iter()
+        .map(|pane| match state::last_turn(pane.pane_id()) {
+            Some(turn) => format!("{} — idle {}", handle_label(pane), ago(now - turn.at)),
+            None => format!("{} — no turns yet", handle_label(pane)),
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+fn status() -> String {
+    let now = OffsetDateTime::now_utc();
+    let describe = |p| match p {
+        Presence::AtDesk => "at desk",
+        Presence::Away => "away",
+    };
+    let presence = match state::manual_presence() {
+        Some(p) => format!("{} (manual)", describe(p)),
+        None => describe(presence::detect()).to_string(),
+    };
+    let mut waiting: Vec<(OffsetDateTime, String)> = AgentDirectory::TmuxProcessScan
+        .discover()
+        .iter()
+        .filter_map(|pane| {
+            Some((
+                state::waiting_since(pane.pane_id())?,
+                pane.label().to_string(),
+            ))
+        })
+        .collect();
+    waiting.sort();
+    let waits = if waiting.is_empty() {
+        "Nothing is waiting on you.".to_string()
+    } else {
+        let panes: Vec<String> = waiting
+            .iter()
+            .map(|(since, label)| format!("{label} ({})", ago(now - *since)))
+            .collect();
+        format!("Waiting on you: {}", panes.join(", "))
+    };
+    format!("Presence: {presence}\n{waits}")
+}
+
+fn peek(pane: &str, lines: usize) -> Result<String, CommandError> {
+    let agent = resolve_pane_arg(pane)?;
+    let text = super::tmux::capture_pane(agent.pane_id(), lines)
+        .ok_or_else(|| CommandError::Capture(agent.label().to_string()))?;
+    Ok(format!("[{}]\n{text}", agent.label()))
+}
+
+// ---------------------------------------------------------------------------
+// Control keys — asked for, then confirmed with `!yes`
+// ---------------------------------------------------------------------------
+
+fn key_name(key: ControlKey) -> &'static str {
+    match key {
+        ControlKey::Stop => "Escape",
+        ControlKey::Interrupt => "Ctrl-C",
+        ControlKey::Enter => "Enter",
+    }
+}
+
+fn request_control(key: ControlKey, pane: &str) -> Result<Answer, CommandError> {
+    let agent = resolve_pane_arg(pane)?;
+    let text = format!(
+        "Press {} in [{}]? Reply !yes within {}s to confirm, or !no.",
+        key_name(key),
+        agent.label(),
+        CONFIRM_WINDOW.whole_seconds()
+    );
+    let target = ControlTarget {
+        pane_id: agent.pane_id().to_string(),
+        label: agent.label().to_string(),
+        key,
+    };
+    Ok(Answer {
+        text,
+        control: Some(ControlStep::Requested(target)),
+    })
+}
+
+/// The pending request, if it can still be confirmed at `now`.
+fn confirmable(
+    pending: Option<(ControlKeyRequested, OffsetDateTime)>,
+    now: OffsetDateTime,
+) -> Result<ControlKeyRequested, CommandError> {
+    let (request, at) = pending.ok_or(CommandError::NothingPending)?;
+    if now - at > CONFIRM_WINDOW {
+        return Err(CommandError::Expired {
+            key: key_name(request.key),
+            label: request.label,
+        });
+    }
+    Ok(request)
+}
+
+fn confirm_control(now: OffsetDateTime) -> Result<Answer, CommandError> {
+    let request = confirmable(state::pending_control(), now)?;
+    let agent = AgentAddress::TmuxPane {
+        pane_id: request.pane_id.clone(),
+        label: request.label.clone(),
+    };
+    // The pane id may have been reused by a shell since; only press keys in
+    // a live agent.
+    let directory = AgentDirectory::TmuxProcessScan;
+    if !directory.is_alive(&agent) {
+        let available = directory
+            .discover()
+            .iter()
+            .map(|p| p.label().to_string())
+            .collect();
+        return Err(RouteError::PaneGone {
+            label: request.label,
+            available,
+        }
+        .into());
+    }
+    let key = key_name(request.key);
+    if !super::tmux::send_control_key(&request.pane_id, request.key) {
+        return Err(CommandError::KeyFailed {
+            key,
+            label: request.label,
+        });
+    }
+    Ok(Answer {
+        text: format!("✓ Pressed {key} in [{}]", request.label),
+        control: Some(ControlStep::Sent(ControlTarget {
+            pane_id: request.pane_id,
+            label: request.label,
+            key: request.key,
+        })),
+    })
+}
+
+fn cancel_control() -> Result<Answer, CommandError> {
+    let (request, _) = state::pending_control().ok_or(CommandError::NothingPending)?;
+    Ok(Answer {
+        text: format!(
+            "Cancelled {} in [{}].",
+            key_name(request.key),
+            request.label
+        ),
+        control: Some(ControlStep::Cancelled),
+    })
+}
+
+/// A duration as its largest whole unit, e.g. `45s`, `12m`, `3h`, `2d`.
+fn ago(elapsed: time::Duration) -> String {
+    let secs = elapsed.whole_seconds().max(0);
+    match secs {
+        s if s < 60 => format!("{s}s"),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86_400),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Option<Result<Command, CommandError>> {
+        Command::parse(text)
+    }
+
+    #[test]
+    fn parse_recognises_commands_and_leaves_replies_alone() {
+        assert!(parse("[harold] ship it").is_none());
+        assert!(parse("ship it!").is_none());
+        assert_eq!(parse("!list").unwrap().unwrap(), Command::List);
+        assert_eq!(parse(" !STATUS ").unwrap().unwrap(), Command::Status);
+        assert_eq!(parse("!help me").unwrap().unwrap(), Command::Help);
+        assert_eq!(
+            parse("!peek harold").unwrap().unwrap(),
+            Command::Peek {
+                pane: "harold".into(),
+                lines: PEEK_DEFAULT_LINES,
+            }
+        );
+        assert_eq!(
+            parse("!peek api:0.1 500").unwrap().unwrap(),
+            Command::Peek {
+                pane: "api:0.1".into(),
+                lines: PEEK_MAX_LINES,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_reports_unknown_commands_and_bad_arguments() {
+        let err = |text| parse(text).unwrap().unwrap_err().to_string();
+        assert_eq!(
+            err("!deploy"),
+            "Unknown command !deploy. Send !help for the list."
+        );
+        assert_eq!(err("!peek"), "Usage: !peek <pane> [n]");
+        assert_eq!(err("!peek api lots"), "Usage: !peek <pane> [n]");
+        assert_eq!(err("!peek api 0"), "Usage: !peek <pane> [n]");
+    }
+
+    #[test]
+    fn parse_control_keys_and_confirmation() {
+        assert_eq!(
+            parse("!interrupt api").unwrap().unwrap(),
+            Command::Control {
+                key: ControlKey::Interrupt,
+                pane: "api".into(),
+            }
+        );
+        assert_eq!(parse("!stop api").unwrap().unwrap().name(), "stop");
+        assert_eq!(parse("!Yes").unwrap().unwrap(), Command::Confirm);
+        assert_eq!(parse("!no").unwrap().unwrap(), Command::Cancel);
+        assert_eq!(
+            parse("!enter").unwrap().unwrap_err().to_string(),
+            "Usage: !stop, !interrupt or !enter <pane>"
+        );
+    }
+
+    #[test]
+    fn confirmable_only_within_the_window() {
+        let at = OffsetDateTime::UNIX_EPOCH;
+        let request = ControlKeyRequested {
+            trace_id: "t".into(),
+            pane_id: "%1".into(),
+            label: "api:0.1".into(),
+            key: ControlKey::Stop,
+        };
+        let pending = Some((request.clone(), at));
+
+        assert_eq!(
+            confirmable(pending.clone(), at + time::Duration::seconds(30)).unwrap(),
+            request
+        );
+        assert_eq!(
+            confirmable(pending, at + time::Duration::minutes(2))
+                .unwrap_err()
+                .to_string(),
+            "The request to press Escape in [api:0.1] expired. Send it again."
+        );
+        assert!(matches!(
+            confirmable(None, at),
+            Err(CommandError::NothingPending)
+        ));
+    }
+
+    #[test]
+    fn ago_uses_the_largest_whole_unit() {
+        assert_eq!(ago(time::Duration::seconds(45)), "45s");
+        assert_eq!(ago(time::Duration::minutes(12)), "12m");
+        assert_eq!(ago(time::Duration::hours(3)), "3h");
+        assert_eq!(ago(time::Duration::days(2)), "2d");
+        assert_eq!(ago(time::Duration::seconds(-5)), "0s");
+    }
+}
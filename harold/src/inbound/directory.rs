@@ -1,10 +1,12 @@
+use serde::{Deserialize, Serialize};
+
 // ---------------------------------------------------------------------------
 // AgentAddress — the address *is* the inbound channel
 // ---------------------------------------------------------------------------
 
 /// How to reach an agent session. Each variant knows how to relay a message
 /// to the agent it represents.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AgentAddress {
     TmuxPane { pane_id: String, label: String },
 }
@@ -34,8 +36,7 @@ impl AgentAddress {
         }
     }
 
-    #[cfg(test)]
-    pub(crate) fn pane_id(&self) -> &str {
+    pub fn pane_id(&self) -> &str {
         match self {
             AgentAddress::TmuxPane { pane_id, .. } => pane_id,
         }
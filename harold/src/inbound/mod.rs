@@ -1,35 +1,36 @@
+pub mod commands;
 pub mod directory;
 pub(crate) mod tmux;
 
-use std::process::Command;
-use std::sync::Mutex;
+use tokio::process::Command;
+use tracing::{info, warn};
 
-use tracing::info;
-
-use crate::outbound::imessage::send_imessage;
+use crate::outbound::send_away_text;
+use crate::proc::{Tool, run_blocking};
 use crate::settings::get_settings;
+use crate::state;
+use crate::store::{ReplyReceived, RouteMethod};
 use crate::util::ai_cli_env;
 
 pub use directory::AgentAddress;
 use directory::AgentDirectory;
 
 // ---------------------------------------------------------------------------
-// State — agent routing (in-memory; Harold owns all routing state)
+// State — agent routing (folded from AwayNotificationSent events; see state.rs)
 // ---------------------------------------------------------------------------
 
-static LAST_AWAY_NOTIFICATION_SOURCE_AGENT: Mutex<Option<AgentAddress>> = Mutex::new(None);
-
-pub(crate) fn set_last_away_notification_source_agent(addr: AgentAddress) {
-    *LAST_AWAY_NOTIFICATION_SOURCE_AGENT.lock().unwrap() = Some(addr);
+fn get_last_away_notification_source_agent() -> Option<AgentAddress> {
+    state::last_away_notification_source_agent()
 }
 
-fn get_last_away_notification_source_agent() -> Option<AgentAddress> {
-    LAST_AWAY_NOTIFICATION_SOURCE_AGENT.lock().unwrap().clone()
+#[cfg(test)]
+pub(crate) fn set_last_away_notification_source_agent(addr: AgentAddress) {
+    state::set_last_away_notification_source_agent(Some(addr));
 }
 
 #[cfg(test)]
 pub(crate) fn clear_routing_state() {
-    *LAST_AWAY_NOTIFICATION_SOURCE_AGENT.lock().unwrap() = None;
+    state::set_last_away_notification_source_agent(None);
 }
 
 // ---------------------------------------------------------------------------
@@ -47,6 +48,137 @@ pub(crate) fn parse_tag(text: &str) -> (Option<&str>, &str) {
     (None, text)
 }
 
+// ---------------------------------------------------------------------------
+// Handles — `#3 do X` or `3: do X`, from an away notification's `#3 [label]`
+// ---------------------------------------------------------------------------
+
+/// The handle a reply starts with and the rest of the text. The digits must be
+/// followed by whitespace (after an optional `:`), so `10:30 works` is not one.
+pub(crate) fn parse_handle(text: &str) -> Option<(u32, &str)> {
+    let text = text.trim_start();
+    let (hashed, rest) = match text.strip_prefix('#') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+    let handle = rest[..digits].parse().ok()?;
+    let rest = &rest[digits..];
+    let rest = match rest.strip_prefix(':') {
+        Some(rest) => rest,
+        None if hashed => rest,
+        None => return None,
+    };
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let body = rest.trim();
+    (!body.is_empty()).then_some((handle, body))
+}
+
+/// `pane`'s label, prefixed with its handle once it has one: `#3 harold:0.1`.
+pub(crate) fn handle_label(pane: &AgentAddress) -> String {
+    match state::pane_handle(pane.pane_id()) {
+        Some((handle, _)) => format!("#{handle} {}", pane.label()),
+        None => pane.label().to_string(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Multi-target tags — `[all]`, `[a,b]`, `[proj-*]`
+// ---------------------------------------------------------------------------
+
+/// Whether `tag` names several panes rather than one.
+pub(crate) fn is_multi_tag(tag: &str) -> bool {
+    tag.trim().eq_ignore_ascii_case("all") || tag.contains([',', '*', '?'])
+}
+
+/// Case-insensitive glob match: `*` is any run of characters, `?` any one.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.to_lowercase().chars().collect();
+    let t: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // Where the last `*` was, and how much of the text it has taken so far.
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((star_pi, star_ti)) = star {
+            pi = star_pi + 1;
+            ti = star_ti + 1;
+            star = Some((star_pi, star_ti + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Every pane a multi-target tag names, in pane order and without repeats.
+/// Each comma-separated part — `all`, a glob, or a label matched like a single
+/// tag — must match at least one pane; the unmatched part is returned otherwise.
+pub(crate) fn resolve_multi<'a>(
+    tag: &str,
+    panes: &'a [AgentAddress],
+) -> Result<Vec<&'a AgentAddress>, String> {
+    let mut selected = vec![false; panes.len()];
+    for part in tag.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let matched: Vec<usize> = if part.eq_ignore_ascii_case("all") {
+            (0..panes.len()).collect()
+        } else if part.contains(['*', '?']) {
+            (0..panes.len())
+                .filter(|&i| glob_match(part, panes[i].label()))
+                .collect()
+        } else {
+            resolve_pane(Some(part), "", panes)
+                .and_then(|(p, ..)| panes.iter().position(|q| q.same_target(p)))
+                .into_iter()
+                .collect()
+        };
+        if matched.is_empty() {
+            return Err(part.to_string());
+        }
+        for i in matched {
+            selected[i] = true;
+        }
+    }
+    Ok(panes
+        .iter()
+        .zip(selected)
+        .filter_map(|(p, s)| s.then_some(p))
+        .collect())
+}
+
+/// The live agents a multi-target tag names. Panes that died since discovery
+/// are left out; it is an error only if none are left.
+fn resolve_targets(tag: &str) -> Result<Vec<AgentAddress>, RouteError> {
+    let directory = AgentDirectory::TmuxProcessScan;
+    let panes = directory.discover();
+    if panes.is_empty() {
+        return Err(RouteError::NoAgents);
+    }
+    let labels = || panes.iter().map(|p| p.label().to_string()).collect();
+    let matched = resolve_multi(tag, &panes).map_err(|part| RouteError::NoMatch {
+        tag: part,
+        available: labels(),
+    })?;
+    let live: Vec<AgentAddress> = matched
+        .into_iter()
+        .filter(|p| directory.is_alive(p))
+        .cloned()
+        .collect();
+    if live.is_empty() {
+        return Err(RouteError::Unresolved {
+            available: labels(),
+        });
+    }
+    info!(tag, targets = ?live.iter().map(|p| p.label()).collect::<Vec<_>>(), "resolved multi-target tag");
+    Ok(live)
+}
+
 // ---------------------------------------------------------------------------
 // Semantic routing via AI CLI
 // ---------------------------------------------------------------------------
@@ -79,21 +211,20 @@ pub(crate) fn semantic_resolve(body: &str, panes: &[AgentAddress]) -> Option<(us
          If no explicit routing intent, reply: none"
     );
 
-    let out = Command::new(cli)
-        .args([
-            "-p",
-            &prompt,
-            "--model",
-            "sonnet",
-            "--max-turns",
-            "1",
-            "--settings",
-            r#"{"disableAllHooks":true}"#,
-        ])
-        .env_remove("CLAUDECODE")
-        .envs(ai_cli_env())
-        .output()
-        .ok()?;
+    let mut cmd = Command::new(cli);
+    cmd.args([
+        "-p",
+        &prompt,
+        "--model",
+        "sonnet",
+        "--max-turns",
+        "1",
+        "--settings",
+        r#"{"disableAllHooks":true}"#,
+    ])
+    .env_remove("CLAUDECODE")
+    .envs(ai_cli_env());
+    let out = run_blocking(cmd, Tool::AiCli).ok()?;
 
     if !out.status.success() {
         let stderr = String::from_utf8_lossy(&out.stderr);
@@ -142,21 +273,21 @@ pub(crate) fn resolve_pane<'a>(
     tag: Option<&str>,
     body: &str,
     panes: &'a [AgentAddress],
-) -> Option<(&'a AgentAddress, String)> {
+) -> Option<(&'a AgentAddress, String, RouteMethod)> {
     let pane_labels: Vec<&str> = panes.iter().map(|p| p.label()).collect();
     info!(available_panes = ?pane_labels, tag = ?tag, "resolving pane");
 
     if let Some(tag) = tag {
         if let Some(p) = panes.iter().find(|p| p.label() == tag) {
             info!(pane = %p.label(), "resolved via exact tag match");
-            return Some((p, body.to_string()));
+            return Some((p, body.to_string(), RouteMethod::ExactTag));
         }
         let tag_lc = tag.to_lowercase();
         let result = panes
             .iter()
             .find(|p| p.label().to_lowercase().contains(&tag_lc))
-            .map(|p| (p, body.to_string()));
-        if let Some((p, _)) = &result {
+            .map(|p| (p, body.to_string(), RouteMethod::TagSubstring));
+        if let Some((p, ..)) = &result {
             info!(pane = %p.label(), "resolved via tag substring match");
         } else {
             info!(tag, "no pane matched tag");
@@ -166,14 +297,14 @@ pub(crate) fn resolve_pane<'a>(
 
     if let Some((idx, cleaned)) = semantic_resolve(body, panes) {
         info!(pane = %panes[idx].label(), "resolved via semantic match");
-        return Some((&panes[idx], cleaned));
+        return Some((&panes[idx], cleaned, RouteMethod::Semantic));
     }
     info!("semantic resolve returned none");
 
     if let Some(last) = get_last_away_notification_source_agent() {
         if let Some(p) = panes.iter().find(|p| p.same_target(&last)) {
             info!(pane = %p.label(), "resolved via last notification source agent");
-            return Some((p, body.to_string()));
+            return Some((p, body.to_string(), RouteMethod::LastAwayNotification));
         }
         info!(last_agent = %last.label(), "last notification source agent no longer alive");
     } else {
@@ -185,7 +316,7 @@ pub(crate) fn resolve_pane<'a>(
         .find(|p| p.label().to_lowercase().contains("my-agent"))
     {
         info!(pane = %p.label(), "resolved via my-agent fallback");
-        return Some((p, body.to_string()));
+        return Some((p, body.to_string(), RouteMethod::MyAgentFallback));
     }
 
     info!("resolution failed — no matching agent");
@@ -193,51 +324,244 @@ pub(crate) fn resolve_pane<'a>(
 }
 
 // ---------------------------------------------------------------------------
-// Route a received reply — called from projector
+// Target resolution — shared by reply routing and the SendToAgent RPC
 // ---------------------------------------------------------------------------
 
-pub fn route_reply(text: &str) {
+#[derive(Debug, thiserror::Error)]
+pub enum RouteError {
+    #[error("No active agent sessions found.")]
+    NoAgents,
+    #[error("No pane matching '{tag}'. Available: {}", .available.join(", "))]
+    NoMatch { tag: String, available: Vec<String> },
+    #[error("No active pane found. Available: {}", .available.join(", "))]
+    Unresolved { available: Vec<String> },
+    #[error("Pane {label} is no longer active. Available: {}", .available.join(", "))]
+    PaneGone {
+        label: String,
+        available: Vec<String>,
+    },
+    #[error("No pane has handle #{handle}. Available: {}", .available.join(", "))]
+    UnknownHandle { handle: u32, available: Vec<String> },
+}
+
+impl RouteError {
+    pub fn available(&self) -> &[String] {
+        match self {
+            RouteError::NoAgents => &[],
+            RouteError::NoMatch { available, .. }
+            | RouteError::Unresolved { available }
+            | RouteError::PaneGone { available, .. }
+            | RouteError::UnknownHandle { available, .. } => available,
+        }
+    }
+}
+
+/// Resolve `tag`/`body` to a live agent, the body to relay to it, and how it matched.
+pub fn resolve_target(
+    tag: Option<&str>,
+    body: &str,
+) -> Result<(AgentAddress, String, RouteMethod), RouteError> {
     let directory = AgentDirectory::TmuxProcessScan;
-    info!(text, "route_reply entered");
-    let (tag, body) = parse_tag(text);
     let panes = directory.discover();
 
     if panes.is_empty() {
-        send_imessage("No active agent sessions found.");
-        return;
+        return Err(RouteError::NoAgents);
     }
 
+    let labels = |skip: Option<&AgentAddress>| {
+        panes
+            .iter()
+            .filter(|p| skip.is_none_or(|s| !p.same_target(s)))
+            .map(|p| p.label().to_string())
+            .collect::<Vec<_>>()
+    };
+
     match resolve_pane(tag, body, &panes) {
-        None => {
-            let available = panes
-                .iter()
-                .map(|p| p.label())
-                .collect::<Vec<_>>()
-                .join(", ");
-            let msg = match tag {
-                Some(t) => format!("No pane matching '{t}'. Available: {available}"),
-                None => format!("No active pane found. Available: {available}"),
-            };
-            send_imessage(&msg);
-        }
-        Some((agent, cleaned_body)) => {
+        None => Err(match tag {
+            Some(t) => RouteError::NoMatch {
+                tag: t.to_string(),
+                available: labels(None),
+            },
+            None => RouteError::Unresolved {
+                available: labels(None),
+            },
+        }),
+        Some((agent, cleaned_body, method)) => {
             if !directory.is_alive(agent) {
-                let available = panes
-                    .iter()
-                    .filter(|p| !p.same_target(agent))
-                    .map(|p| p.label())
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                send_imessage(&format!(
-                    "Pane {} is no longer active. Available: {}",
-                    agent.label(),
-                    available
-                ));
-                return;
+                return Err(RouteError::PaneGone {
+                    label: agent.label().to_string(),
+                    available: labels(Some(agent)),
+                });
             }
-            info!(label = %agent.label(), "routing reply");
+            Ok((agent.clone(), cleaned_body, method))
+        }
+    }
+}
+
+/// The agent a reply token or handle names, if it is still alive.
+fn resolve_known(
+    agent: AgentAddress,
+    body: &str,
+    method: RouteMethod,
+) -> Result<(AgentAddress, String, RouteMethod), RouteError> {
+    let directory = AgentDirectory::TmuxProcessScan;
+    if directory.is_alive(&agent) {
+        info!(pane = %agent.label(), ?method, "resolved via known agent");
+        return Ok((agent, body.to_string(), method));
+    }
+    let available = directory
+        .discover()
+        .iter()
+        .filter(|p| !p.same_target(&agent))
+        .map(|p| p.label().to_string())
+        .collect();
+    Err(RouteError::PaneGone {
+        label: agent.label().to_string(),
+        available,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Route a received reply — called from projector
+// ---------------------------------------------------------------------------
+
+/// The live agent a command's pane argument names: `#3` is a handle, a bare
+/// `3` is one if some pane holds it, and anything else is matched like a tag.
+pub(crate) fn resolve_pane_arg(pane: &str) -> Result<AgentAddress, RouteError> {
+    let explicit = pane.strip_prefix('#');
+    let handle = explicit.unwrap_or(pane).parse::<u32>().ok();
+    match handle {
+        Some(handle) if explicit.is_some() || state::agent_for_handle(handle).is_some() => {
+            resolve_handle(handle, "").map(|(agent, ..)| agent)
+        }
+        _ => resolve_target(Some(pane), "").map(|(agent, ..)| agent),
+    }
+}
+
+/// The agent behind `handle`, or the live panes to pick from instead.
+fn resolve_handle(
+    handle: u32,
+    body: &str,
+) -> Result<(AgentAddress, String, RouteMethod), RouteError> {
+    if let Some(agent) = state::agent_for_handle(handle) {
+        return resolve_known(agent, body, RouteMethod::Handle);
+    }
+    let available = AgentDirectory::TmuxProcessScan
+        .discover()
+        .iter()
+        .map(handle_label)
+        .collect();
+    Err(RouteError::UnknownHandle { handle, available })
+}
+
+/// Route a reply to its agents. Returns each agent and how it was matched, or
+/// why routing failed, for the projector to record. A `#3` handle names the
+/// agent exactly. So does a known reply token, though an explicit `[tag]` in
+/// the text takes precedence over it. A multi-target tag relays to every live
+/// match, with one confirmation.
+pub fn route_reply(reply: &ReplyReceived) -> Result<Vec<(AgentAddress, RouteMethod)>, RouteError> {
+    info!(text = %reply.text, reply_token = ?reply.reply_token, "route_reply entered");
+    let (tag, body) = parse_tag(&reply.text);
+    // `#3` is always a handle; a bare `3:` only when some pane holds it, so a
+    // numbered line such as `1: run tests first` is routed like any text.
+    if let Some((handle, body)) = parse_handle(&reply.text).filter(|&(handle, _)| {
+        reply.text.trim_start().starts_with('#') || state::agent_for_handle(handle).is_some()
+    }) {
+        return deliver(resolve_handle(handle, body));
+    }
+    if let Some(tag) = tag.filter(|t| is_multi_tag(t)) {
+        return match resolve_targets(tag) {
+            Err(e) => {
+                send_away_text(&e.to_string());
+                Err(e)
+            }
+            Ok(agents) => {
+                for agent in &agents {
+                    agent.relay(&format!("📱 {body}"));
+                }
+                let labels: Vec<String> =
+                    agents.iter().map(|a| format!("[{}]", a.label())).collect();
+                send_away_text(&format!("✓ Delivered to {}", labels.join(", ")));
+                Ok(agents
+                    .into_iter()
+                    .map(|a| (a, RouteMethod::MultiTag))
+                    .collect())
+            }
+        };
+    }
+    let token_agent = match tag {
+        None => reply
+            .reply_token
+            .as_deref()
+            .and_then(state::agent_for_reply_token),
+        Some(_) => None,
+    };
+    deliver(match token_agent {
+        Some(agent) => resolve_known(agent, body, RouteMethod::ReplyToken),
+        None => resolve_target(tag, body),
+    })
+}
+
+/// Relay a resolved reply and confirm it, or report why it could not be.
+fn deliver(
+    resolved: Result<(AgentAddress, String, RouteMethod), RouteError>,
+) -> Result<Vec<(AgentAddress, RouteMethod)>, RouteError> {
+    match resolved {
+        Err(e) => {
+            send_away_text(&e.to_string());
+            Err(e)
+        }
+        Ok((agent, cleaned_body, method)) => {
+            info!(label = %agent.label(), ?method, "routing reply");
             agent.relay(&format!("📱 {cleaned_body}"));
-            send_imessage(&format!("✓ Delivered to [{}]", agent.label()));
+            send_away_text(&format!("✓ Delivered to [{}]", agent.label()));
+            Ok(vec![(agent, method)])
+        }
+    }
+}
+
+/// What a reply turned out to be.
+pub enum ReplyOutcome {
+    Routed(Result<Vec<(AgentAddress, RouteMethod)>, RouteError>),
+    /// A `!command`: its name, the error sent back if it failed, and the
+    /// control key step it took.
+    Command {
+        name: String,
+        error: Option<String>,
+        control: Option<commands::ControlStep>,
+    },
+}
+
+/// Handle a reply: a `!command` is answered on the away channel, anything else
+/// is routed to an agent.
+pub fn handle_reply(reply: &ReplyReceived) -> ReplyOutcome {
+    let Some(parsed) = commands::Command::parse(&reply.text) else {
+        return ReplyOutcome::Routed(route_reply(reply));
+    };
+    let (name, result) = match parsed {
+        Ok(command) => {
+            info!(command = command.name(), "running reply command");
+            (command.name().to_string(), command.run())
+        }
+        Err(e) => ("unknown".to_string(), Err(e)),
+    };
+    match result {
+        Ok(answer) => {
+            send_away_text(&answer.text);
+            ReplyOutcome::Command {
+                name,
+                error: None,
+                control: answer.control,
+            }
+        }
+        Err(e) => {
+            warn!(error = %e, "reply command failed");
+            send_away_text(&e.to_string());
+            ReplyOutcome::Command {
+                name,
+                error: Some(e.to_string()),
+                control: None,
+            }
         }
     }
 }
@@ -259,10 +583,11 @@ mod tests {
     use std::sync::Mutex;
 
     use crate::inbound::{
-        AgentAddress, clear_routing_state, parse_tag, resolve_pane,
-        set_last_away_notification_source_agent,
+        AgentAddress, RouteError, clear_routing_state, glob_match, is_multi_tag, parse_handle,
+        parse_tag, resolve_multi, resolve_pane, set_last_away_notification_source_agent,
     };
     use crate::settings::init_settings_for_test;
+    use crate::store::RouteMethod;
 
     /// Serialises tests that mutate global routing state.
     static ROUTING_TEST_LOCK: Mutex<()> = Mutex::new(());
@@ -281,6 +606,20 @@ mod tests {
         assert_eq!(body, "hello world");
     }
 
+    #[test]
+    fn parse_handle_accepts_hash_and_colon_forms() {
+        assert_eq!(parse_handle("#3 do X"), Some((3, "do X")));
+        assert_eq!(parse_handle("#12: run it "), Some((12, "run it")));
+        assert_eq!(parse_handle("3: do X"), Some((3, "do X")));
+        // Ordinary text that happens to start with digits.
+        assert_eq!(parse_handle("10:30 works for me"), None);
+        assert_eq!(parse_handle("3 more tests"), None);
+        assert_eq!(parse_handle("#3"), None);
+        assert_eq!(parse_handle("#3:"), None);
+        assert_eq!(parse_handle("#x do X"), None);
+        assert_eq!(parse_handle("[main] #3 do X"), None);
+    }
+
     #[test]
     fn parse_tag_without_tag() {
         let (tag, body) = parse_tag("just a message");
@@ -300,7 +639,9 @@ mod tests {
         let panes = vec![tmux("%1", "work:0.0"), tmux("%2", "home:0.1")];
         let result = resolve_pane(Some("work:0.0"), "hi", &panes);
         assert!(result.is_some());
-        assert_eq!(result.unwrap().0.pane_id(), "%1");
+        let (agent, _, method) = result.unwrap();
+        assert_eq!(agent.pane_id(), "%1");
+        assert_eq!(method, RouteMethod::ExactTag);
     }
 
     #[test]
@@ -308,7 +649,9 @@ mod tests {
         let panes = vec![tmux("%1", "work:0.0"), tmux("%2", "home:0.1")];
         let result = resolve_pane(Some("home"), "hi", &panes);
         assert!(result.is_some());
-        assert_eq!(result.unwrap().0.pane_id(), "%2");
+        let (agent, _, method) = result.unwrap();
+        assert_eq!(agent.pane_id(), "%2");
+        assert_eq!(method, RouteMethod::TagSubstring);
     }
 
     #[test]
@@ -339,4 +682,50 @@ mod tests {
         let result = resolve_pane(Some("nonexistent"), "hi", &panes);
         assert!(result.is_none());
     }
+
+    #[test]
+    fn glob_match_handles_stars_and_question_marks() {
+        assert!(glob_match("proj-*", "proj-api:0.1"));
+        assert!(glob_match("PROJ-*", "proj-api:0.1"));
+        assert!(glob_match("*:0.?", "web:0.2"));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(!glob_match("proj-*", "other:0.1"));
+        assert!(!glob_match("*:1.?", "web:0.2"));
+    }
+
+    #[test]
+    fn resolve_multi_unions_parts_in_pane_order() {
+        let panes = vec![
+            tmux("%1", "proj-api:0.0"),
+            tmux("%2", "proj-web:0.1"),
+            tmux("%3", "notes:0.0"),
+        ];
+        let ids = |tag| {
+            resolve_multi(tag, &panes)
+                .unwrap()
+                .iter()
+                .map(|p| p.pane_id().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert!(is_multi_tag("all") && is_multi_tag("a,b") && is_multi_tag("proj-*"));
+        assert!(!is_multi_tag("proj-api"));
+        assert_eq!(ids("ALL"), ["%1", "%2", "%3"]);
+        assert_eq!(ids("notes, proj-api"), ["%1", "%3"]);
+        assert_eq!(ids("proj-*,proj-web"), ["%1", "%2"]);
+        assert_eq!(resolve_multi("proj-*,db", &panes).unwrap_err(), "db");
+    }
+
+    #[test]
+    fn route_error_messages_list_available_panes() {
+        let err = RouteError::NoMatch {
+            tag: "web".into(),
+            available: vec!["work:0.0".into(), "home:0.1".into()],
+        };
+        assert_eq!(
+            err.to_string(),
+            "No pane matching 'web'. Available: work:0.0, home:0.1"
+        );
+        assert_eq!(err.available(), ["work:0.0", "home:0.1"]);
+        assert!(RouteError::NoAgents.available().is_empty());
+    }
 }
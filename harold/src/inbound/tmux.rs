@@ -1,7 +1,17 @@
-use std::process::Command;
+use std::process::Output;
 
+use tokio::process::Command;
 use tracing::info;
 
+use crate::proc::{ProcError, Tool, run_blocking};
+use crate::store::ControlKey;
+
+fn tmux(args: &[&str]) -> Result<Output, ProcError> {
+    let mut cmd = Command::new("tmux");
+    cmd.args(args);
+    run_blocking(cmd, Tool::Tmux)
+}
+
 // ---------------------------------------------------------------------------
 // Process detection
 // ---------------------------------------------------------------------------
@@ -21,15 +31,12 @@ pub(crate) fn node_semver_process(cmd: &str) -> bool {
 // ---------------------------------------------------------------------------
 
 pub(crate) fn scan_live_panes() -> Vec<super::directory::AgentAddress> {
-    let out = match Command::new("tmux")
-        .args([
-            "list-panes",
-            "-a",
-            "-F",
-            "#{pane_id}|#{session_name}:#{window_index}.#{pane_index}|#{pane_current_command}",
-        ])
-        .output()
-    {
+    let out = match tmux(&[
+        "list-panes",
+        "-a",
+        "-F",
+        "#{pane_id}|#{session_name}:#{window_index}.#{pane_index}|#{pane_current_command}",
+    ]) {
         Ok(o) => o,
         Err(_) => return vec![],
     };
@@ -59,16 +66,14 @@ pub(crate) fn scan_live_panes() -> Vec<super::directory::AgentAddress> {
 }
 
 pub(crate) fn is_pane_alive(pane_id: &str) -> bool {
-    Command::new("tmux")
-        .args([
-            "display-message",
-            "-t",
-            pane_id,
-            "-p",
-            "#{pane_current_command}",
-        ])
-        .output()
-        .is_ok_and(|o| node_semver_process(String::from_utf8_lossy(&o.stdout).trim()))
+    tmux(&[
+        "display-message",
+        "-t",
+        pane_id,
+        "-p",
+        "#{pane_current_command}",
+    ])
+    .is_ok_and(|o| node_semver_process(String::from_utf8_lossy(&o.stdout).trim()))
 }
 
 // ---------------------------------------------------------------------------
@@ -99,6 +104,29 @@ pub(crate) fn strip_control(text: &str) -> String {
     out
 }
 
+// ---------------------------------------------------------------------------
+// Pane capture
+// ---------------------------------------------------------------------------
+
+/// The last `lines` lines a pane shows, with trailing blank lines dropped and
+/// control sequences stripped.
+pub(crate) fn capture_pane(pane_id: &str, lines: usize) -> Option<String> {
+    let start = format!("-{lines}");
+    let out = tmux(&["capture-pane", "-p", "-J", "-t", pane_id, "-S", &start]).ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    Some(last_lines(
+        &strip_control(&String::from_utf8_lossy(&out.stdout)),
+        lines,
+    ))
+}
+
+fn last_lines(text: &str, n: usize) -> String {
+    let lines: Vec<&str> = text.trim_end().lines().map(str::trim_end).collect();
+    lines[lines.len().saturating_sub(n)..].join("\n")
+}
+
 // ---------------------------------------------------------------------------
 // tmux relay
 // ---------------------------------------------------------------------------
@@ -106,12 +134,20 @@ pub(crate) fn strip_control(text: &str) -> String {
 pub(crate) fn relay_to_tmux_pane(pane_id: &str, text: &str) {
     info!(pane_id, text, "relay_to_tmux_pane");
     let safe = strip_control(text);
-    let _ = Command::new("tmux")
-        .args(["send-keys", "-t", pane_id, "-l", &safe])
-        .status();
-    let _ = Command::new("tmux")
-        .args(["send-keys", "-t", pane_id, "Enter"])
-        .status();
+    let _ = tmux(&["send-keys", "-t", pane_id, "-l", &safe]);
+    let _ = tmux(&["send-keys", "-t", pane_id, "Enter"]);
+}
+
+/// Send one allowlisted key. The tmux key name comes from this fixed table,
+/// never from reply text.
+pub(crate) fn send_control_key(pane_id: &str, key: ControlKey) -> bool {
+    let name = match key {
+        ControlKey::Stop => "Escape",
+        ControlKey::Interrupt => "C-c",
+        ControlKey::Enter => "Enter",
+    };
+    info!(pane_id, key = name, "send_control_key");
+    tmux(&["send-keys", "-t", pane_id, name]).is_ok_and(|out| out.status.success())
 }
 
 // ---------------------------------------------------------------------------
@@ -136,6 +172,13 @@ mod tests {
         assert_eq!(output, "clean");
     }
 
+    #[test]
+    fn last_lines_drops_trailing_blank_lines() {
+        let screen = "one\ntwo  \nthree\n\n\n";
+        assert_eq!(last_lines(screen, 2), "two\nthree");
+        assert_eq!(last_lines(screen, 10), "one\ntwo\nthree");
+    }
+
     #[test]
     fn node_semver_process_matches_node_version() {
         assert!(node_semver_process("16.20.1"));
@@ -1,18 +1,26 @@
+mod chat_db;
+mod feed;
 mod inbound;
-mod listener;
 mod outbound;
+mod presence;
+mod proc;
 mod projector;
 mod settings;
+mod sources;
+mod state;
 mod store;
+mod summarizer;
 mod telemetry;
 mod tmux;
 mod util;
 
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use settings::{get_settings, init_settings};
 use telemetry::init_telemetry;
-use tokio::sync::watch;
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status, transport::Server};
 use tracing::{Instrument, info, info_span};
 
@@ -20,15 +28,44 @@ pub mod harold {
     tonic::include_proto!("harold");
 }
 
+use harold::harold_client::HaroldClient;
 use harold::harold_server::{Harold, HaroldServer};
-use harold::{TurnCompleteRequest, TurnCompleteResponse};
+use harold::{
+    Agent, HaroldEvent, ListAgentsRequest, ListAgentsResponse, PresenceMode, SendToAgentRequest,
+    SendToAgentResponse, SetPresenceRequest, SetPresenceResponse, TurnCompleteRequest,
+    TurnCompleteResponse, WatchEventsRequest,
+};
+use inbound::directory::AgentDirectory;
+
+/// Snapshot of every discovered agent with its tmux and turn state. Blocking (tmux queries).
+fn list_agents() -> Vec<Agent> {
+    AgentDirectory::TmuxProcessScan
+        .discover()
+        .into_iter()
+        .map(|addr| {
+            let pane_id = addr.pane_id().to_string();
+            let last = state::last_turn(&pane_id);
+            Agent {
+                session: tmux::pane_session(&pane_id).unwrap_or_default(),
+                attached: tmux::is_session_attached(&pane_id),
+                last_turn_completed_ms: last.as_ref().map(|t| util::unix_ms(t.at)),
+                last_user_prompt: last.map(|t| t.last_user_prompt),
+                label: addr.label().to_string(),
+                pane_id,
+            }
+        })
+        .collect()
+}
 
 struct HaroldService {
     store: Arc<events::EventStore>,
+    shutdown: watch::Receiver<()>,
 }
 
 #[tonic::async_trait]
 impl Harold for HaroldService {
+    type WatchEventsStream = ReceiverStream<Result<HaroldEvent, Status>>;
+
     async fn turn_complete(
         &self,
         request: Request<TurnCompleteRequest>,
@@ -66,6 +103,154 @@ impl Harold for HaroldService {
         .instrument(span)
         .await
     }
+
+    async fn watch_events(
+        &self,
+        request: Request<WatchEventsRequest>,
+    ) -> Result<Response<Self::WatchEventsStream>, Status> {
+        let req = request.into_inner();
+        let from_version = match req.from_version {
+            Some(v) => v,
+            None => store::next_version(),
+        };
+        let event_types: HashSet<String> = req.event_types.into_iter().collect();
+
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(
+            feed::tail_events(
+                Arc::clone(&self.store),
+                from_version,
+                event_types,
+                tx,
+                self.shutdown.clone(),
+            )
+            .instrument(info_span!("grpc_watch_events")),
+        );
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn list_agents(
+        &self,
+        _request: Request<ListAgentsRequest>,
+    ) -> Result<Response<ListAgentsResponse>, Status> {
+        let span = info_span!("grpc_list_agents");
+        let agents = tokio::task::spawn_blocking(move || {
+            let _g = span.entered();
+            list_agents()
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "agent discovery task panicked");
+            Status::internal("agent discovery failed")
+        })?;
+        info!(count = agents.len(), "agents listed");
+        Ok(Response::new(ListAgentsResponse { agents }))
+    }
+
+    async fn send_to_agent(
+        &self,
+        request: Request<SendToAgentRequest>,
+    ) -> Result<Response<SendToAgentResponse>, Status> {
+        let req = request.into_inner();
+        let trace_id = uuid::Uuid::new_v4().to_string();
+        let span = info_span!("grpc_send_to_agent", trace_id = %trace_id);
+
+        let blocking_span = span.clone();
+        let resolved = tokio::task::spawn_blocking(move || {
+            let _g = blocking_span.entered();
+            let (tag, body) = match req.tag.as_deref() {
+                Some(tag) => (Some(tag), req.body.trim()),
+                None => inbound::parse_tag(&req.body),
+            };
+            info!(tag = ?tag, "send to agent received");
+            inbound::resolve_target(tag, body).map(|(agent, body, _)| {
+                agent.relay(&body);
+                (agent, body)
+            })
+        })
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "send to agent task panicked");
+            Status::internal("send to agent failed")
+        })?;
+
+        async {
+            let (agent, text) = match resolved {
+                Ok(delivered) => delivered,
+                Err(e) => {
+                    info!(error = %e, "send to agent not delivered");
+                    return Ok(Response::new(SendToAgentResponse {
+                        delivered: false,
+                        label: String::new(),
+                        available_panes: e.available().to_vec(),
+                        error: e.to_string(),
+                    }));
+                }
+            };
+            info!(label = %agent.label(), "send to agent delivered");
+
+            let event = store::AgentMessageSent {
+                pane_id: agent.pane_id().to_string(),
+                pane_label: agent.label().to_string(),
+                text,
+            };
+            // The text is already in the pane; a failed append only loses the history entry.
+            if let Err(e) = store::append_agent_message_sent(&self.store, &event).await {
+                tracing::warn!(error = %e, "failed to append AgentMessageSent event");
+            }
+
+            Ok(Response::new(SendToAgentResponse {
+                delivered: true,
+                label: event.pane_label,
+                error: String::new(),
+                available_panes: vec![],
+            }))
+        }
+        .instrument(span)
+        .await
+    }
+
+    async fn set_presence(
+        &self,
+        request: Request<SetPresenceRequest>,
+    ) -> Result<Response<SetPresenceResponse>, Status> {
+        let mode = request.into_inner().mode();
+        let span = info_span!("grpc_set_presence", ?mode);
+
+        async {
+            let event = store::ManualPresenceSet {
+                presence: match mode {
+                    PresenceMode::Auto => None,
+                    PresenceMode::AtDesk => Some(store::Presence::AtDesk),
+                    PresenceMode::Away => Some(store::Presence::Away),
+                },
+            };
+            store::append_manual_presence_set(&self.store, &event)
+                .await
+                .map_err(|e| {
+                    tracing::error!(error = %e, "failed to append ManualPresenceSet event");
+                    Status::internal("event store write failed")
+                })?;
+
+            let presence = tokio::task::spawn_blocking(presence::detect)
+                .await
+                .map_err(|e| {
+                    tracing::error!(error = %e, "presence detection task panicked");
+                    Status::internal("presence detection failed")
+                })?;
+            info!(?presence, "presence set");
+            let presence = match presence {
+                store::Presence::AtDesk => "at_desk",
+                store::Presence::Away => "away",
+            };
+            Ok(Response::new(SetPresenceResponse {
+                presence: presence.into(),
+            }))
+        }
+        .instrument(span)
+        .await
+    }
 }
 
 async fn shutdown_signal() {
@@ -79,8 +264,8 @@ async fn shutdown_signal() {
 }
 
 fn run_diagnostics(delay_secs: u64) {
-    use outbound::{imessage::notify_away, is_screen_locked, tts::notify_at_desk};
-    use store::TurnCompleted;
+    use outbound::{OutboundChannel, TurnSummary, tts::notify_at_desk};
+    use store::{Presence, TurnCompleted};
 
     let turn = TurnCompleted {
         pane_id: "diag".into(),
@@ -97,8 +282,11 @@ fn run_diagnostics(delay_secs: u64) {
         std::thread::sleep(std::time::Duration::from_secs(delay_secs));
     }
 
-    let locked = is_screen_locked();
-    println!("screen_locked : {locked}");
+    for (name, reading) in presence::readings() {
+        println!("presence      : {name:<7} → {reading:?}");
+    }
+    let away = presence::detect() == Presence::Away;
+    println!("away          : {away}");
 
     let cfg = get_settings();
     println!(
@@ -106,6 +294,37 @@ fn run_diagnostics(delay_secs: u64) {
         cfg.imessage.recipient.as_deref().unwrap_or("(not set)"),
         cfg.imessage.handle_ids,
     );
+    println!(
+        "Telegram      : chat_id={} bot_token={} api_base={}",
+        cfg.telegram
+            .chat_id
+            .map_or("(not set)".into(), |id| id.to_string()),
+        if cfg.telegram.bot_token.is_some() {
+            "set"
+        } else {
+            "(not set)"
+        },
+        cfg.telegram.api_base,
+    );
+    println!(
+        "Email         : smtp={} imap={} to={}",
+        cfg.email.smtp_url.as_deref().unwrap_or("(not set)"),
+        cfg.email.imap_url.as_deref().unwrap_or("(not set)"),
+        cfg.email.to.as_deref().unwrap_or("(not set)"),
+    );
+    println!(
+        "Matrix        : homeserver={} room_id={} allowed_user={} access_token={}",
+        cfg.matrix.homeserver.as_deref().unwrap_or("(not set)"),
+        cfg.matrix.room_id.as_deref().unwrap_or("(not set)"),
+        cfg.matrix.allowed_user.as_deref().unwrap_or("(not set)"),
+        if cfg.matrix.access_token.is_some() {
+            "set"
+        } else {
+            "(not set)"
+        },
+    );
+    println!("away channel  : {:?}", cfg.notify.away_channel);
+    println!("inbound       : {:?}", sources::configured());
     println!(
         "TTS           : command={} voice={:?}",
         cfg.tts.command, cfg.tts.voice,
@@ -136,30 +355,45 @@ fn run_diagnostics(delay_secs: u64) {
         }
     }
 
-    println!("\n--- Testing notify path (screen_locked={locked}) ---");
-    if !locked {
+    println!("\n--- Testing notify path (away={away}) ---");
+    if !away {
         println!("Running TTS...");
-        notify_at_desk(&turn, "diag");
-        println!("TTS done");
-        return;
-    }
-    if cfg.imessage.recipient.is_none() {
-        println!("iMessage NOT sent: recipient not configured");
+        let outcome = notify_at_desk(&turn, "diag");
+        println!("TTS done: {outcome:?}");
         return;
     }
-    println!("Sending iMessage...");
-    notify_away(&turn, "diag");
-    println!("iMessage sent (check your phone)");
+    println!("Sending away notification...");
+    let outcome = OutboundChannel::away().notify(&turn, "diag", &TurnSummary::new(&turn));
+    println!("Away outcome: {outcome:?} (check your phone)");
 
     println!("\nDone.");
 }
 
+/// `harold --presence <mode>` — set the override on the running daemon over gRPC.
+async fn set_presence(mode: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let mode = match mode {
+        Some("away") => PresenceMode::Away,
+        Some("at-desk") => PresenceMode::AtDesk,
+        Some("auto") => PresenceMode::Auto,
+        _ => return Err("--presence expects one of: away, at-desk, auto".into()),
+    };
+    let addr = get_settings().grpc.addr()?;
+    let mut client = HaroldClient::connect(format!("http://{addr}")).await?;
+    let resp = client
+        .set_presence(SetPresenceRequest { mode: mode.into() })
+        .await?
+        .into_inner();
+    println!("presence: {}", resp.presence);
+    Ok(())
+}
+
 fn print_help() {
     println!("harold — agent notification and reply routing daemon\n");
     println!("USAGE:");
     println!("  harold                  Start the Harold daemon");
-    println!("  harold --diagnostics [--delay [N]]  Test screen lock, TTS, and iMessage config");
+    println!("  harold --diagnostics [--delay [N]]  Test presence, TTS, and iMessage config");
     println!("                                      --delay defaults to 10s if no value given");
+    println!("  harold --presence <away|at-desk|auto>  Override presence on the running daemon");
     println!("  harold --help           Show this help\n");
     println!("ENVIRONMENT:");
     println!("  HAROLD_CONFIG_DIR       Path to config directory (default: ./config)");
@@ -207,28 +441,38 @@ async fn async_main(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>>
         } else {
             0
         };
-        run_diagnostics(delay);
+        // Diagnostics run tools synchronously, which must happen off the async workers.
+        tokio::task::spawn_blocking(move || run_diagnostics(delay)).await?;
         return Ok(());
     }
 
+    if let Some(pos) = args.iter().position(|a| a == "--presence") {
+        return set_presence(args.get(pos + 1).map(String::as_str)).await;
+    }
+
     let store_path = cfg.store.resolved_path();
     let store = store::open_store(&store_path).await?;
+    let snapshot_path = state::snapshot_path(&store_path);
+    state::rebuild(&store, &snapshot_path).await?;
 
     let addr = cfg.grpc.addr()?;
     info!(address = %addr, "Harold listening");
 
     // Shutdown channel: sender closes on signal, receivers see the channel close.
     let (shutdown_tx, shutdown_rx) = watch::channel(());
+    proc::init_shutdown(shutdown_rx.clone());
 
     let projector_handle = tokio::spawn(projector::run_projector(
         Arc::clone(&store),
         shutdown_rx.clone(),
     ));
-    let listener_handle = tokio::spawn(listener::listen(Arc::clone(&store), shutdown_rx));
+    let source_handles = sources::spawn_all(&store, &shutdown_rx);
 
     Server::builder()
         .add_service(HaroldServer::new(HaroldService {
             store: Arc::clone(&store),
+            // Streaming RPCs end on shutdown so graceful shutdown doesn't wait on them.
+            shutdown: shutdown_rx,
         }))
         .serve_with_shutdown(addr, async {
             shutdown_signal().await;
@@ -240,7 +484,11 @@ async fn async_main(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>>
 
     // Wait for tasks to stop before checkpointing — checkpoint requires no active connections.
     let _ = projector_handle.await;
-    let _ = listener_handle.await;
+    for handle in source_handles {
+        let _ = handle.await;
+    }
+
+    state::save_snapshot(&store, &snapshot_path).await;
 
     // Checkpoint WAL: flushes all WAL pages to the main db files so next open is clean.
     info!("checkpointing WAL");
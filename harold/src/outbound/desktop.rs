@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tokio_stream::StreamExt;
+use tracing::{info, warn};
+use zbus::Connection;
+use zbus::zvariant::Value;
+
+use super::imessage::split_body;
+use super::{NotifyOutcome, TurnSummary};
+use crate::settings::get_settings;
+use crate::store::TurnCompleted;
+use crate::tmux;
+
+// ---------------------------------------------------------------------------
+// org.freedesktop.Notifications over D-Bus
+// ---------------------------------------------------------------------------
+
+#[zbus::proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications",
+    gen_blocking = false
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+}
+
+const ACTION_JUMP: &str = "jump";
+const ACTION_DISMISS: &str = "dismiss";
+
+/// A notification ready to show: title, body and the pane its actions act on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DesktopMessage {
+    pub title: String,
+    pub body: String,
+    pub pane_id: Option<String>,
+}
+
+impl DesktopMessage {
+    fn for_turn(turn: &TurnCompleted, summary: &str) -> Self {
+        let (main_body, question) = split_body(summary);
+        let body = match question {
+            Some(q) => format!("{}\n\n{q}", main_body.trim()),
+            None => main_body.trim().to_string(),
+        };
+        Self {
+            title: format!("[{}] {}", turn.pane_label, turn.main_context),
+            body,
+            pane_id: Some(turn.pane_id.clone()),
+        }
+    }
+
+    /// Action key/label pairs, flattened as the spec wants them.
+    fn actions(&self) -> Vec<&'static str> {
+        let mut actions = Vec::new();
+        if self.pane_id.is_some() {
+            actions.extend([ACTION_JUMP, "Jump to pane"]);
+        }
+        actions.extend([ACTION_DISMISS, "Dismiss"]);
+        actions
+    }
+}
+
+/// What the user did with a notification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Response {
+    Action(String),
+    Closed,
+    TimedOut,
+}
+
+/// A notification's signal streams. Subscribed before `Notify`, so a click or
+/// close that arrives straight after it is not missed.
+pub(crate) struct Signals {
+    actions: ActionInvokedStream,
+    closed: NotificationClosedStream,
+}
+
+pub(crate) async fn subscribe(conn: &Connection) -> zbus::Result<Signals> {
+    let proxy = NotificationsProxy::new(conn).await?;
+    Ok(Signals {
+        actions: proxy.receive_action_invoked().await?,
+        closed: proxy.receive_notification_closed().await?,
+    })
+}
+
+/// Show `msg` and return its notification id.
+pub(crate) async fn show(conn: &Connection, msg: &DesktopMessage) -> zbus::Result<u32> {
+    let cfg = &get_settings().desktop;
+    let proxy = NotificationsProxy::new(conn).await?;
+    proxy
+        .notify(
+            &cfg.app_name,
+            0,
+            "",
+            &msg.title,
+            &msg.body,
+            &msg.actions(),
+            HashMap::new(),
+            cfg.expire_timeout_ms,
+        )
+        .await
+}
+
+/// Wait for the user to act on notification `id`, or for it to close.
+pub(crate) async fn wait_for_response(
+    signals: Signals,
+    id: u32,
+    wait: Duration,
+) -> zbus::Result<Response> {
+    let Signals {
+        mut actions,
+        mut closed,
+    } = signals;
+    let response = async {
+        loop {
+            tokio::select! {
+                Some(signal) = actions.next() => {
+                    let args = signal.args()?;
+                    if args.id == id {
+                        return Ok(Response::Action(args.action_key));
+                    }
+                }
+                Some(signal) = closed.next() => {
+                    if signal.args()?.id == id {
+                        return Ok(Response::Closed);
+                    }
+                }
+                else => return Ok(Response::Closed),
+            }
+        }
+    };
+    tokio::time::timeout(wait, response)
+        .await
+        .unwrap_or(Ok(Response::TimedOut))
+}
+
+async fn handle_response(conn: Connection, signals: Signals, id: u32, pane_id: Option<String>) {
+    let wait = Duration::from_secs(get_settings().desktop.action_wait_secs);
+    match wait_for_response(signals, id, wait).await {
+        Ok(Response::Action(key)) if key == ACTION_JUMP => {
+            let Some(pane_id) = pane_id else { return };
+            info!(pane_id, "desktop notification: jumping to pane");
+            if let Err(e) = tokio::task::spawn_blocking(move || tmux::select_pane(&pane_id)).await {
+                warn!(error = %e, "select pane task panicked");
+            }
+        }
+        Ok(Response::Action(key)) if key == ACTION_DISMISS => {
+            if let Ok(proxy) = NotificationsProxy::new(&conn).await {
+                let _ = proxy.close_notification(id).await;
+            }
+        }
+        Ok(response) => info!(?response, "desktop notification finished"),
+        Err(e) => warn!(error = %e, "desktop notification: lost D-Bus signals"),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Channel entry points (blocking — called from the projector's blocking tasks)
+// ---------------------------------------------------------------------------
+
+fn send(msg: DesktopMessage) -> NotifyOutcome {
+    let timeout = Duration::from_secs(get_settings().timeouts.dbus_secs);
+    let handle = tokio::runtime::Handle::current();
+    let shown = handle.block_on(tokio::time::timeout(timeout, async {
+        let conn = Connection::session().await?;
+        let signals = subscribe(&conn).await?;
+        let id = show(&conn, &msg).await?;
+        Ok::<_, zbus::Error>((conn, signals, id))
+    }));
+    match shown {
+        Ok(Ok((conn, signals, id))) => {
+            info!(id, "desktop notification shown");
+            // Actions arrive later; the notification must not hold up the lane.
+            handle.spawn(handle_response(conn, signals, id, msg.pane_id));
+            NotifyOutcome::Sent {
+                channel: "desktop",
+                source_agent: None,
+                reply_tokens: vec![],
+            }
+        }
+        Ok(Err(e)) => {
+            warn!(error = %e, "desktop notification failed");
+            NotifyOutcome::Failed {
+                channel: "desktop",
+                reason: e.to_string(),
+            }
+        }
+        Err(_) => {
+            warn!("desktop notification timed out");
+            NotifyOutcome::Failed {
+                channel: "desktop",
+                reason: format!("D-Bus call timed out after {timeout:?}"),
+            }
+        }
+    }
+}
+
+pub fn notify_at_desk(
+    turn: &TurnCompleted,
+    _trace_id: &str,
+    summary: &TurnSummary,
+) -> NotifyOutcome {
+    let summary = summary.get(&get_settings().ai.desktop_summary);
+    send(DesktopMessage::for_turn(turn, summary))
+}
+
+pub fn notify_digest_at_desk(digest: &str) -> NotifyOutcome {
+    send(DesktopMessage {
+        title: "Harold".into(),
+        body: digest.into(),
+        pane_id: None,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Tests — against a private dbus-daemon with a fake notification server
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::sync::{Arc, Mutex};
+
+    use zbus::object_server::SignalEmitter;
+
+    use super::*;
+
+    struct PrivateBus(Child);
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+        }
+    }
+
+    /// Start a throwaway session bus and return its address.
+    fn private_bus() -> (PrivateBus, String) {
+        let mut child = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("dbus-daemon is installed");
+        let mut address = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        (PrivateBus(child), address.trim().to_string())
+    }
+
+    /// (summary, body, actions) of each `Notify` call.
+    type Shown = Arc<Mutex<Vec<(String, String, Vec<String>)>>>;
+
+    #[derive(Default, Clone)]
+    struct FakeServer {
+        shown: Shown,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.Notifications")]
+    impl FakeServer {
+        #[allow(clippy::too_many_arguments)]
+        async fn notify(
+            &self,
+            _app_name: &str,
+            _replaces_id: u32,
+            _app_icon: &str,
+            summary: &str,
+            body: &str,
+            actions: Vec<String>,
+            _hints: HashMap<&str, Value<'_>>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            self.shown
+                .lock()
+                .unwrap()
+                .push((summary.into(), body.into(), actions));
+            7
+        }
+
+        async fn close_notification(&self, _id: u32) {}
+
+        #[zbus(signal)]
+        async fn action_invoked(
+            emitter: &SignalEmitter<'_>,
+            id: u32,
+            action_key: &str,
+        ) -> zbus::Result<()>;
+    }
+
+    fn turn() -> TurnCompleted {
+        TurnCompleted {
+            pane_id: "%3".into(),
+            pane_label: "harold:0.1".into(),
+            last_user_prompt: "fix it".into(),
+            assistant_message: String::new(),
+            main_context: "main".into(),
+        }
+    }
+
+    #[test]
+    fn message_has_label_summary_and_question() {
+        let msg = DesktopMessage::for_turn(&turn(), "Fixed the race. Should I push?");
+        assert_eq!(msg.title, "[harold:0.1] main");
+        assert_eq!(msg.body, "Fixed the race.\n\nShould I push?");
+        assert_eq!(
+            msg.actions(),
+            vec!["jump", "Jump to pane", "dismiss", "Dismiss"]
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "needs dbus-daemon; run with --ignored"]
+    async fn shows_notification_and_reports_action() {
+        crate::settings::init_settings_for_test();
+        let (_bus, address) = private_bus();
+
+        let server = FakeServer::default();
+        let server_conn = zbus::connection::Builder::address(address.as_str())
+            .unwrap()
+            .name("org.freedesktop.Notifications")
+            .unwrap()
+            .serve_at("/org/freedesktop/Notifications", server.clone())
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        let client = zbus::connection::Builder::address(address.as_str())
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        let msg = DesktopMessage::for_turn(&turn(), "Fixed the race. Should I push?");
+        let signals = subscribe(&client).await.unwrap();
+        let id = show(&client, &msg).await.unwrap();
+        assert_eq!(id, 7);
+        let (title, body, actions) = server.shown.lock().unwrap()[0].clone();
+        assert_eq!(title, "[harold:0.1] main");
+        assert_eq!(body, "Fixed the race.\n\nShould I push?");
+        assert_eq!(actions[0], "jump");
+
+        // Emitted before anyone waits: the subscription made before `show` keeps it.
+        let emitter = SignalEmitter::new(&server_conn, "/org/freedesktop/Notifications").unwrap();
+        FakeServer::action_invoked(&emitter, id, "jump")
+            .await
+            .unwrap();
+
+        let response = wait_for_response(signals, id, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(response, Response::Action("jump".into()));
+    }
+}
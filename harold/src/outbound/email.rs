@@ -0,0 +1,426 @@
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc2822;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+use super::imessage::split_body;
+use super::{NotifyOutcome, TurnSummary, pane_tag};
+use crate::inbound::AgentAddress;
+use crate::proc::{ProcError, Tool, run_blocking_with_input};
+use crate::settings::get_settings;
+use crate::store::{SkipReason, TurnCompleted};
+use crate::util::curl_config;
+
+// ---------------------------------------------------------------------------
+// SMTP and IMAP through curl
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailError {
+    #[error(transparent)]
+    Proc(#[from] ProcError),
+    #[error("curl failed: {0}")]
+    Curl(String),
+    #[error("cannot stage message for upload: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Mail server endpoints and credentials (`[email]`).
+#[derive(Debug, Clone)]
+pub(crate) struct Mailer {
+    smtp_url: String,
+    /// Includes the folder, e.g. `imaps://imap.example.com/INBOX`.
+    imap_url: String,
+    credentials: Option<String>,
+    require_tls: bool,
+}
+
+impl Mailer {
+    pub fn new(smtp_url: &str, imap_url: &str, credentials: Option<(&str, &str)>) -> Self {
+        Self {
+            smtp_url: smtp_url.to_string(),
+            imap_url: imap_url.trim_end_matches('/').to_string(),
+            credentials: credentials.map(|(user, pass)| format!("{user}:{pass}")),
+            require_tls: false,
+        }
+    }
+
+    /// The configured mailer, if both server URLs are set.
+    pub fn from_settings() -> Option<Self> {
+        let cfg = &get_settings().email;
+        let credentials = cfg.username.as_deref().zip(cfg.password.as_deref());
+        let mut mailer = Self::new(
+            cfg.smtp_url.as_deref()?,
+            cfg.imap_url.as_deref()?,
+            credentials,
+        );
+        mailer.require_tls = cfg.require_tls;
+        Some(mailer)
+    }
+
+    /// Run curl against `url` with `args` and return its output. The URL and
+    /// credentials go in a config on stdin, so the password is never on argv.
+    fn curl(&self, url: &str, args: &[&str]) -> Result<Vec<u8>, EmailError> {
+        let mut cmd = Command::new("curl");
+        cmd.args(["--silent", "--show-error", "-K", "-"]);
+        if self.require_tls {
+            // STARTTLS on smtp:// and imap://; implied by smtps:// and imaps://.
+            cmd.arg("--ssl-reqd");
+        }
+        cmd.args(args);
+        let mut config = vec![("url", url)];
+        if let Some(credentials) = &self.credentials {
+            config.push(("user", credentials));
+        }
+        let out = run_blocking_with_input(cmd, Tool::Http, curl_config(&config))?;
+        if out.status.success() {
+            Ok(out.stdout)
+        } else {
+            Err(EmailError::Curl(
+                String::from_utf8_lossy(&out.stderr).trim().to_string(),
+            ))
+        }
+    }
+
+    /// Submit `message` (a complete RFC 822 message) from `from` to `to`. Stdin
+    /// carries the curl config, so the message is uploaded from a private file.
+    pub fn send(&self, from: &str, to: &str, message: &str) -> Result<(), EmailError> {
+        let path = std::env::temp_dir().join(format!("harold-mail-{}.eml", uuid::Uuid::new_v4()));
+        let written = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .and_then(|mut file| file.write_all(message.as_bytes()));
+        let sent = written.map_err(EmailError::from).and_then(|()| {
+            self.curl(
+                &self.smtp_url,
+                &[
+                    "--mail-from",
+                    address(from),
+                    "--mail-rcpt",
+                    address(to),
+                    "--upload-file",
+                    &path.to_string_lossy(),
+                ],
+            )
+        });
+        let _ = std::fs::remove_file(&path);
+        sent.map(drop)
+    }
+
+    /// UIDs in the folder above `after`, ascending. With `after = None`, just
+    /// the highest UID — where a first poll starts.
+    pub fn search_after(&self, after: Option<u32>) -> Result<Vec<u32>, EmailError> {
+        let query = match after {
+            Some(uid) => format!("UID SEARCH UID {}:*", uid + 1),
+            None => "UID SEARCH UID *".to_string(),
+        };
+        let stdout = self.curl(&self.imap_url, &["--request", &query])?;
+        let mut uids = parse_search(&String::from_utf8_lossy(&stdout));
+        // `n:*` always matches the highest UID, even when it is below n.
+        uids.retain(|&uid| after.is_none_or(|a| uid > a));
+        Ok(uids)
+    }
+
+    /// The raw message with `uid`.
+    pub fn fetch(&self, uid: u32) -> Result<Vec<u8>, EmailError> {
+        self.curl(&format!("{};UID={uid}", self.imap_url), &[])
+    }
+}
+
+/// UIDs from an untagged `* SEARCH 4 7 9` response.
+fn parse_search(response: &str) -> Vec<u32> {
+    let mut uids: Vec<u32> = response
+        .lines()
+        .filter_map(|l| l.trim().strip_prefix("* SEARCH"))
+        .flat_map(|l| l.split_whitespace().filter_map(|n| n.parse().ok()))
+        .collect();
+    uids.sort_unstable();
+    uids
+}
+
+/// `Name <a@b>` → `a@b`.
+pub(crate) fn address(mailbox: &str) -> &str {
+    match (mailbox.rfind('<'), mailbox.rfind('>')) {
+        (Some(open), Some(close)) if open < close => mailbox[open + 1..close].trim(),
+        _ => mailbox.trim(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Composing notifications
+// ---------------------------------------------------------------------------
+
+/// Header carrying the reply token. Replies rarely copy it, so the token also
+/// goes in the `Message-ID` (echoed in `In-Reply-To`) and the subject.
+pub(crate) const REPLY_TOKEN_HEADER: &str = "X-Harold-Reply-Token";
+
+/// A short random token identifying one notification, e.g. `3f9a2c1b`.
+pub(crate) fn new_reply_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..8].to_string()
+}
+
+/// RFC 2047 encoded-word for non-ASCII header text.
+fn encode_header(text: &str) -> String {
+    if text.is_ascii() {
+        text.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", BASE64.encode(text))
+    }
+}
+
+pub(crate) struct Email<'a> {
+    pub from: &'a str,
+    pub to: &'a str,
+    pub subject: String,
+    pub body: String,
+    pub reply_token: Option<&'a str>,
+}
+
+impl Email<'_> {
+    /// Render as an RFC 822 message with a quoted-printable UTF-8 body.
+    pub fn render(&self, date: OffsetDateTime) -> String {
+        let domain = address(self.from).rsplit('@').next().unwrap_or("harold");
+        let mut headers = vec![
+            format!("From: {}", self.from),
+            format!("To: {}", self.to),
+            format!("Subject: {}", encode_header(&self.subject)),
+            format!("Date: {}", date.format(&Rfc2822).unwrap_or_default()),
+        ];
+        match self.reply_token {
+            Some(token) => {
+                headers.push(format!("Message-ID: <harold-{token}@{domain}>"));
+                headers.push(format!("{REPLY_TOKEN_HEADER}: {token}"));
+            }
+            None => headers.push(format!(
+                "Message-ID: <harold-{}@{domain}>",
+                uuid::Uuid::new_v4().simple()
+            )),
+        }
+        headers.extend([
+            "MIME-Version: 1.0".to_string(),
+            "Content-Type: text/plain; charset=utf-8".to_string(),
+            "Content-Transfer-Encoding: quoted-printable".to_string(),
+        ]);
+        let body = quoted_printable::encode_to_str(self.body.replace('\n', "\r\n"));
+        format!("{}\r\n\r\n{body}\r\n", headers.join("\r\n"))
+    }
+}
+
+fn turn_email<'a>(
+    from: &'a str,
+    to: &'a str,
+    turn: &TurnCompleted,
+    summary: &str,
+    token: &'a str,
+) -> Email<'a> {
+    let (main_body, question) = split_body(summary);
+    let mut body = main_body.trim().to_string();
+    if let Some(q) = question {
+        body.push_str("\n\n");
+        body.push_str(q);
+    }
+    body.push_str(&format!(
+        "\n\nReply to this email to answer [{}].",
+        turn.pane_label
+    ));
+    Email {
+        from,
+        to,
+        subject: format!("{} {} [#{token}]", pane_tag(turn), turn.main_context),
+        body,
+        reply_token: Some(token),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Channel entry points
+// ---------------------------------------------------------------------------
+
+/// The configured mailer, sender and recipient, or `None` if any is missing.
+fn configured() -> Option<(Mailer, &'static str, &'static str)> {
+    let cfg = &get_settings().email;
+    Some((
+        Mailer::from_settings()?,
+        cfg.from.as_deref()?,
+        cfg.to.as_deref()?,
+    ))
+}
+
+fn send_plain(subject: &str, body: &str) -> Result<(), EmailError> {
+    let Some((mailer, from, to)) = configured() else {
+        return Ok(());
+    };
+    let email = Email {
+        from,
+        to,
+        subject: subject.to_string(),
+        body: body.to_string(),
+        reply_token: None,
+    };
+    mailer.send(from, to, &email.render(OffsetDateTime::now_utc()))
+}
+
+/// Send a plain email (confirmation/error) to the configured recipient.
+pub(crate) fn send_email(msg: &str) {
+    info!(msg, "sending email");
+    if let Err(e) = send_plain("Harold", msg) {
+        warn!(error = %e, "email send failed");
+    }
+}
+
+pub fn notify_away(turn: &TurnCompleted, _trace_id: &str, summary: &TurnSummary) -> NotifyOutcome {
+    let Some((mailer, from, to)) = configured() else {
+        warn!("email smtp_url, imap_url, from or to not configured");
+        return NotifyOutcome::Skipped(SkipReason::NotConfigured);
+    };
+    let summary = summary.get(&get_settings().ai.imessage_summary);
+    let token = new_reply_token();
+    let email = turn_email(from, to, turn, summary, &token);
+
+    match mailer.send(from, to, &email.render(OffsetDateTime::now_utc())) {
+        Ok(()) => {
+            info!(reply_token = %token, "email notification sent");
+            NotifyOutcome::Sent {
+                channel: "email",
+                source_agent: Some(AgentAddress::TmuxPane {
+                    pane_id: turn.pane_id.clone(),
+                    label: turn.pane_label.clone(),
+                }),
+                reply_tokens: vec![token],
+            }
+        }
+        Err(e) => {
+            warn!(error = %e, "email notification failed");
+            NotifyOutcome::Failed {
+                channel: "email",
+                reason: e.to_string(),
+            }
+        }
+    }
+}
+
+pub fn notify_digest_away(digest: &str) -> NotifyOutcome {
+    if configured().is_none() {
+        warn!("email smtp_url, imap_url, from or to not configured");
+        return NotifyOutcome::Skipped(SkipReason::NotConfigured);
+    }
+    match send_plain("Harold: offline digest", digest) {
+        Ok(()) => NotifyOutcome::Sent {
+            channel: "email",
+            source_agent: None,
+            reply_tokens: vec![],
+        },
+        Err(e) => NotifyOutcome::Failed {
+            channel: "email",
+            reason: e.to_string(),
+        },
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests — against local SMTP and IMAP stand-ins
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::super::stand_in;
+    use super::*;
+
+    fn turn() -> TurnCompleted {
+        TurnCompleted {
+            pane_id: "%3".into(),
+            pane_label: "harold:0.1".into(),
+            last_user_prompt: "fix it".into(),
+            assistant_message: String::new(),
+            main_context: "main".into(),
+        }
+    }
+
+    #[test]
+    fn turn_email_carries_token_in_subject_and_headers() {
+        let email = turn_email(
+            "Harold <harold@example.com>",
+            "me@example.com",
+            &turn(),
+            "Fixed the race. Should I push?",
+            "3f9a2c1b",
+        );
+        let raw = email.render(OffsetDateTime::UNIX_EPOCH);
+        assert!(raw.contains("Subject: [harold:0.1] main [#3f9a2c1b]\r\n"));
+        assert!(raw.contains("Message-ID: <harold-3f9a2c1b@example.com>\r\n"));
+        assert!(raw.contains("X-Harold-Reply-Token: 3f9a2c1b\r\n"));
+        assert!(raw.contains("Date: Thu, 01 Jan 1970 00:00:00 +0000\r\n"));
+        assert!(raw.contains("\r\n\r\nFixed the race.\r\n\r\nShould I push?"));
+    }
+
+    #[test]
+    fn non_ascii_subject_is_encoded() {
+        assert_eq!(encode_header("plain"), "plain");
+        assert_eq!(encode_header("café"), "=?UTF-8?B?Y2Fmw6k=?=");
+    }
+
+    #[test]
+    fn parse_search_reads_untagged_response() {
+        assert_eq!(parse_search("* SEARCH 9 4 7\r\n"), vec![4, 7, 9]);
+        assert_eq!(parse_search("* SEARCH\r\n"), Vec::<u32>::new());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn send_submits_message_over_smtp() {
+        crate::settings::init_settings_for_test();
+        let (url, rx) = stand_in::smtp();
+        let mailer = Mailer::new(&url, "imap://unused", None);
+        let email = turn_email(
+            "Harold <harold@example.com>",
+            "me@example.com",
+            &turn(),
+            "Done.",
+            "3f9a2c1b",
+        );
+        let raw = email.render(OffsetDateTime::UNIX_EPOCH);
+
+        tokio::task::spawn_blocking(move || {
+            mailer.send("Harold <harold@example.com>", "me@example.com", &raw)
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+        let (rcpts, data) = rx.recv().unwrap();
+        assert_eq!(rcpts, vec!["me@example.com"]);
+        assert!(data.contains("X-Harold-Reply-Token: 3f9a2c1b"), "{data}");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn search_and_fetch_over_imap() {
+        crate::settings::init_settings_for_test();
+        let base = stand_in::imap(
+            vec![
+                (4, "Subject: old\n\nold"),
+                (7, "Subject: Re: x\n\nship it\n"),
+            ],
+            3,
+        );
+        let mailer = Mailer::new("smtp://unused", &format!("{base}/INBOX"), Some(("u", "p")));
+
+        let (first, newer, raw) = tokio::task::spawn_blocking(move || {
+            (
+                mailer.search_after(None).unwrap(),
+                mailer.search_after(Some(7)).unwrap(),
+                mailer.fetch(7).unwrap(),
+            )
+        })
+        .await
+        .unwrap();
+        assert_eq!(first, vec![7]);
+        assert!(newer.is_empty(), "highest uid is not re-reported");
+        assert!(String::from_utf8_lossy(&raw).contains("ship it"));
+    }
+}
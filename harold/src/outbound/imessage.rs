@@ -1,32 +1,78 @@
-use std::process::Command;
-
+use tokio::process::Command;
 use tracing::{info, warn};
 
+use super::{NotifyOutcome, TurnSummary, pane_tag};
+use crate::chat_db::{Reader, sent, with_reader};
 use crate::inbound::AgentAddress;
+use crate::proc::{Tool, run_blocking};
 use crate::settings::get_settings;
-use crate::store::TurnCompleted;
-use crate::util::{ai_cli_env, sanitise_for_applescript};
+use crate::store::{SkipReason, TurnCompleted};
+use crate::util::sanitise_for_applescript;
 
 // ---------------------------------------------------------------------------
 // iMessage helpers
 // ---------------------------------------------------------------------------
 
-/// Low-level iMessage send — delivers `text` as-is (no prefix) to `recipient`.
-pub(crate) fn send_imessage_to(text: &str, recipient: &str) {
-    let safe_text = sanitise_for_applescript(text);
-    let safe_recipient = sanitise_for_applescript(recipient);
-    let escaped = safe_text.replace('\\', "\\\\").replace('"', "\\\"");
-    let escaped_recipient = safe_recipient.replace('\\', "\\\\").replace('"', "\\\"");
-    let script = format!(
-        "tell application \"Messages\" to send \"{escaped}\" to buddy \"{escaped_recipient}\""
-    );
-    let _ = Command::new("osascript").args(["-e", &script]).status();
+/// Low-level iMessage send — delivers `text` as-is (no prefix) to `recipient`,
+/// then claims its chat.db row so the iMessage source does not read it back.
+/// Returns whether osascript reported success.
+pub(crate) fn send_imessage_to(text: &str, recipient: &str) -> bool {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    // A string literal cannot span lines, so lines are joined with `linefeed`.
+    let mut lines: Vec<String> = text.lines().map(sanitise_for_applescript).collect();
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    let literal = lines
+        .iter()
+        .map(|line| format!("\"{}\"", escape(line)))
+        .collect::<Vec<_>>()
+        .join(" & linefeed & ");
+    let safe_text = lines.join("\n");
+    let escaped_recipient = escape(&sanitise_for_applescript(recipient));
+    let script =
+        format!("tell application \"Messages\" to send {literal} to buddy \"{escaped_recipient}\"");
+    let mut cmd = Command::new("osascript");
+    cmd.args(["-e", &script]);
+
+    let _sending = sent::sending();
+    let watermark = with_reader(Reader::max_rowid)
+        .inspect_err(|e| warn!(error = %e, "cannot mark chat.db before sending"))
+        .ok();
+    let sent_ok = match run_blocking(cmd, Tool::Osascript) {
+        Ok(out) if out.status.success() => true,
+        Ok(out) => {
+            warn!(
+                status = %out.status,
+                stderr = %String::from_utf8_lossy(&out.stderr).trim(),
+                "osascript failed to send iMessage"
+            );
+            false
+        }
+        Err(e) => {
+            warn!(error = %e, "osascript failed to send iMessage");
+            false
+        }
+    };
+    match watermark {
+        Some(after_rowid) if sent_ok => sent::claim(after_rowid, &safe_text),
+        None if sent_ok => sent::unclaimed(&safe_text),
+        _ => {}
+    }
+    sent_ok
 }
 
 /// Send an iMessage notification with robot-emoji prefix.
-fn send_raw_imessage(text: &str, recipient: &str) {
+fn send_raw_imessage(text: &str, recipient: &str) -> bool {
     info!(msg = %text, "sending iMessage notification");
-    send_imessage_to(&format!("🤖 {text}"), recipient);
+    send_imessage_to(&format!("{} {text}", sent::NOTIFICATION_PREFIX), recipient)
+}
+
+fn send_failed() -> NotifyOutcome {
+    NotifyOutcome::Failed {
+        channel: "imessage",
+        reason: "osascript could not send the message".into(),
+    }
 }
 
 /// Send a plain iMessage (confirmation/error) to the configured recipient.
@@ -39,32 +85,13 @@ pub(crate) fn send_imessage(msg: &str) {
     send_imessage_to(msg, recipient);
 }
 
-fn query_chat_db_single(db_path: &str, sql: &str) -> Option<String> {
-    // SQL is built only from i64-typed values (handle_id). String interpolation of
-    // non-numeric values must never be added here — use the sqlite3 CLI's `-cmd` flag
-    // or a native library if parameterised queries are ever needed.
-    let out = Command::new("sqlite3")
-        .arg(db_path)
-        .arg(sql)
-        .output()
-        .ok()?;
-    if !out.status.success() {
-        return None;
-    }
-    let s = String::from_utf8_lossy(&out.stdout).trim().to_string();
-    if s.is_empty() { None } else { Some(s) }
-}
-
+/// The last message sent to `handle_id`. A failed read counts as none, so the
+/// notification is sent rather than wrongly deduplicated.
 fn last_outgoing_text(handle_id: i64) -> Option<String> {
-    let db_path = get_settings().chat_db.resolved_path();
-    // handle_id is i64 from settings — safe to interpolate.
-    query_chat_db_single(
-        &db_path,
-        &format!(
-            "SELECT text FROM message WHERE handle_id = {handle_id} AND is_from_me = 1 \
-             ORDER BY ROWID DESC LIMIT 1;"
-        ),
-    )
+    with_reader(|reader| reader.last_outgoing_text(handle_id))
+        .inspect_err(|e| warn!(error = %e, "duplicate check skipped"))
+        .ok()
+        .flatten()
 }
 
 // ---------------------------------------------------------------------------
@@ -87,97 +114,21 @@ pub(crate) fn split_body(body: &str) -> (&str, Option<&str>) {
 }
 
 // ---------------------------------------------------------------------------
-// AI-powered summary for iMessage notifications
-// ---------------------------------------------------------------------------
-
-/// Cap `assistant_message` to 280 chars and flatten newlines into spaces.
-fn truncate_body(assistant_message: &str) -> String {
-    assistant_message
-        .chars()
-        .take(280)
-        .collect::<String>()
-        .replace('\n', " ")
-}
-
-/// Summarise `assistant_message` for iMessage delivery using the AI CLI.
-/// Falls back to [`truncate_body`] if the CLI is not configured or fails.
-fn summarise_for_imessage(assistant_message: &str, last_user_prompt: &str) -> String {
-    let cfg = get_settings();
-    let Some(cli) = cfg.ai.cli_path.as_deref() else {
-        return truncate_body(assistant_message);
-    };
-
-    let safe_msg = assistant_message
-        .replace("</message>", "")
-        .replace("</prompt>", "");
-    let safe_prompt = last_user_prompt
-        .replace("</prompt>", "")
-        .replace("</message>", "");
-    let prompt = format!(
-        "You are writing a phone notification summary.\n\n\
-         USER ASKED:\n<prompt>\n{safe_prompt}\n</prompt>\n\n\
-         ASSISTANT REPLIED:\n<message>\n{safe_msg}\n</message>\n\n\
-         Write 2-3 plain sentences summarising what was done and the outcome.\n\
-         Preserve any question the assistant asked.\n\
-         No code, no markdown, no jargon. Keep it under 280 characters."
-    );
-
-    let out = Command::new(cli)
-        .args([
-            "-p",
-            &prompt,
-            "--model",
-            "sonnet",
-            "--max-turns",
-            "1",
-            "--settings",
-            r#"{"disableAllHooks":true}"#,
-        ])
-        .env_remove("CLAUDECODE")
-        .envs(ai_cli_env())
-        .output();
-
-    match out {
-        Ok(o) if o.status.success() => {
-            let summary = String::from_utf8_lossy(&o.stdout).trim().to_string();
-            if summary.is_empty() {
-                truncate_body(assistant_message)
-            } else {
-                truncate_body(&summary)
-            }
-        }
-        Ok(o) => {
-            let stderr = String::from_utf8_lossy(&o.stderr);
-            warn!(
-                status = %o.status,
-                stderr = %stderr.chars().take(200).collect::<String>(),
-                "summarise_for_imessage: AI CLI failed, falling back to truncation"
-            );
-            truncate_body(assistant_message)
-        }
-        Err(e) => {
-            warn!(error = %e, "summarise_for_imessage: failed to spawn AI CLI, falling back to truncation");
-            truncate_body(assistant_message)
-        }
-    }
-}
-
-// ---------------------------------------------------------------------------
-// Away notification via iMessage — returns the source agent address
+// Away notification via iMessage — reports the source agent address
 // ---------------------------------------------------------------------------
 
-pub fn notify_away(turn: &TurnCompleted, _trace_id: &str) -> Option<AgentAddress> {
+pub fn notify_away(turn: &TurnCompleted, _trace_id: &str, summary: &TurnSummary) -> NotifyOutcome {
     let cfg = get_settings();
     let Some(recipient) = cfg.imessage.recipient.as_deref() else {
         warn!("iMessage recipient not configured");
-        return None;
+        return NotifyOutcome::Skipped(SkipReason::NotConfigured);
     };
-    let body = summarise_for_imessage(&turn.assistant_message, &turn.last_user_prompt);
+    let body = summary.get(&cfg.ai.imessage_summary);
 
-    let (main_body, question) = split_body(&body);
+    let (main_body, question) = split_body(body);
     let message = format!(
-        "[{}] {} ({})",
-        turn.pane_label,
+        "{} {} ({})",
+        pane_tag(turn),
         main_body.trim(),
         turn.main_context
     );
@@ -187,24 +138,53 @@ pub fn notify_away(turn: &TurnCompleted, _trace_id: &str) -> Option<AgentAddress
         .handle_ids
         .first()
         .and_then(|&id| last_outgoing_text(id))
-        .is_some_and(|last| last.trim().trim_start_matches("🤖").trim() == message.trim());
+        .is_some_and(|last| {
+            last.trim()
+                .trim_start_matches(sent::NOTIFICATION_PREFIX)
+                .trim()
+                == message.trim()
+        });
     if is_duplicate {
         info!("iMessage skipped (duplicate)");
-        return None;
+        return NotifyOutcome::Skipped(SkipReason::Duplicate);
     }
 
-    send_raw_imessage(&message, recipient);
+    if !send_raw_imessage(&message, recipient) {
+        return send_failed();
+    }
     info!("iMessage notification sent");
 
-    if let Some(q) = question {
-        send_raw_imessage(q, recipient);
+    // The notification itself went out, so a lost question still counts as sent.
+    if let Some(q) = question
+        && send_raw_imessage(q, recipient)
+    {
         info!("iMessage question sent");
     }
 
-    Some(AgentAddress::TmuxPane {
-        pane_id: turn.pane_id.clone(),
-        label: turn.pane_label.clone(),
-    })
+    NotifyOutcome::Sent {
+        channel: "imessage",
+        source_agent: Some(AgentAddress::TmuxPane {
+            pane_id: turn.pane_id.clone(),
+            label: turn.pane_label.clone(),
+        }),
+        reply_tokens: vec![],
+    }
+}
+
+pub fn notify_digest_away(digest: &str) -> NotifyOutcome {
+    let Some(recipient) = get_settings().imessage.recipient.as_deref() else {
+        warn!("iMessage recipient not configured");
+        return NotifyOutcome::Skipped(SkipReason::NotConfigured);
+    };
+    if !send_raw_imessage(digest, recipient) {
+        return send_failed();
+    }
+    info!("iMessage digest sent");
+    NotifyOutcome::Sent {
+        channel: "imessage",
+        source_agent: None,
+        reply_tokens: vec![],
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -244,24 +224,6 @@ mod tests {
         assert_eq!(q, Some("Shall I open a PR?"));
     }
 
-    #[test]
-    fn truncate_body_caps_at_280_chars_and_flattens_newlines() {
-        use super::truncate_body;
-
-        let short = "Hello world.\nDone.";
-        assert_eq!(truncate_body(short), "Hello world. Done.");
-
-        let long: String = "x".repeat(300);
-        let result = truncate_body(&long);
-        assert_eq!(result.len(), 280);
-
-        // Multi-byte: caps at 280 *characters*, not bytes.
-        let emoji_long: String = "\u{1F600}".repeat(300);
-        let result = truncate_body(&emoji_long);
-        assert_eq!(result.chars().count(), 280);
-        assert!(result.len() > 280);
-    }
-
     #[test]
     fn sanitise_strips_newlines_and_continuation() {
         let result = sanitise_for_applescript("line1\nline2\r¬end");
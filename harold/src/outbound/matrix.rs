@@ -0,0 +1,410 @@
+use std::collections::HashMap;
+
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+use super::imessage::split_body;
+use super::{NotifyOutcome, TurnSummary, pane_tag};
+use crate::inbound::AgentAddress;
+use crate::proc::{ProcError, Tool, run_blocking_with_input};
+use crate::settings::get_settings;
+use crate::store::{SkipReason, TurnCompleted};
+use crate::util::curl_config;
+
+// ---------------------------------------------------------------------------
+// Client-server API — requests go through curl, like every other tool
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, thiserror::Error)]
+pub enum MatrixError {
+    #[error(transparent)]
+    Proc(#[from] ProcError),
+    #[error("curl failed: {0}")]
+    Curl(String),
+    #[error("Matrix API error: {0}")]
+    Api(String),
+    #[error("unexpected Matrix response: {0}")]
+    Malformed(#[from] serde_json::Error),
+}
+
+/// Error body returned with any non-2xx status.
+#[derive(Deserialize)]
+struct ApiError {
+    errcode: String,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SendResponse {
+    event_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SyncResponse {
+    pub next_batch: String,
+    #[serde(default)]
+    pub rooms: Rooms,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct Rooms {
+    #[serde(default)]
+    pub join: HashMap<String, JoinedRoom>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct JoinedRoom {
+    #[serde(default)]
+    pub timeline: Timeline,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct Timeline {
+    #[serde(default)]
+    pub events: Vec<RoomEvent>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct RoomEvent {
+    pub sender: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Unix milliseconds.
+    pub origin_server_ts: i64,
+    #[serde(default)]
+    pub content: MessageContent,
+    #[serde(default)]
+    pub unsigned: Unsigned,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct MessageContent {
+    pub msgtype: Option<String>,
+    pub body: Option<String>,
+    #[serde(rename = "m.relates_to")]
+    pub relates_to: Option<RelatesTo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct RelatesTo {
+    pub rel_type: Option<String>,
+    /// The thread root when `rel_type` is `m.thread`.
+    pub event_id: Option<String>,
+    #[serde(rename = "m.in_reply_to")]
+    pub in_reply_to: Option<InReplyTo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct InReplyTo {
+    pub event_id: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct Unsigned {
+    /// Set only on events this access token sent.
+    pub transaction_id: Option<String>,
+}
+
+impl RoomEvent {
+    /// The notification this message answers: its thread root, or the event a
+    /// plain rich reply points at.
+    pub fn reply_token(&self) -> Option<&str> {
+        let relates_to = self.content.relates_to.as_ref()?;
+        match relates_to.rel_type.as_deref() {
+            Some("m.thread") => relates_to.event_id.as_deref(),
+            _ => relates_to.in_reply_to.as_ref().map(|r| r.event_id.as_str()),
+        }
+    }
+}
+
+/// An access token and the homeserver it belongs to (`[matrix]`).
+#[derive(Debug, Clone)]
+pub(crate) struct Client {
+    homeserver: String,
+    access_token: String,
+}
+
+impl Client {
+    pub fn new(homeserver: &str, access_token: &str) -> Self {
+        Self {
+            homeserver: homeserver.trim_end_matches('/').to_string(),
+            access_token: access_token.to_string(),
+        }
+    }
+
+    /// The configured client, if `matrix.homeserver` and `access_token` are set.
+    pub fn from_settings() -> Option<Self> {
+        let cfg = &get_settings().matrix;
+        Some(Self::new(
+            cfg.homeserver.as_deref()?,
+            cfg.access_token.as_deref()?,
+        ))
+    }
+
+    fn curl(&self, path: &str) -> Command {
+        let url = format!("{}/_matrix/client/v3{path}", self.homeserver);
+        let mut cmd = Command::new("curl");
+        cmd.args(["--silent", "--show-error", "-K", "-", &url]);
+        cmd
+    }
+
+    /// Run a request from [`Client::curl`]. The access token goes in the curl
+    /// config on stdin, never on argv.
+    fn call<T: DeserializeOwned>(&self, cmd: Command, tool: Tool) -> Result<T, MatrixError> {
+        let auth = format!("Authorization: Bearer {}", self.access_token);
+        let out = run_blocking_with_input(cmd, tool, curl_config(&[("header", &auth)]))?;
+        if !out.status.success() {
+            return Err(MatrixError::Curl(
+                String::from_utf8_lossy(&out.stderr).trim().to_string(),
+            ));
+        }
+        if let Ok(err) = serde_json::from_slice::<ApiError>(&out.stdout) {
+            return Err(MatrixError::Api(match err.error {
+                Some(msg) => format!("{}: {msg}", err.errcode),
+                None => err.errcode,
+            }));
+        }
+        Ok(serde_json::from_slice(&out.stdout)?)
+    }
+
+    /// Post a text message to `room_id`, inside the thread rooted at
+    /// `thread_root` if given. Returns the new event id.
+    pub fn send_text(
+        &self,
+        room_id: &str,
+        text: &str,
+        thread_root: Option<&str>,
+    ) -> Result<String, MatrixError> {
+        let mut content = json!({ "msgtype": "m.text", "body": text });
+        if let Some(root) = thread_root {
+            content["m.relates_to"] = json!({
+                "rel_type": "m.thread",
+                "event_id": root,
+                // Clients without thread support show a reply to the root.
+                "is_falling_back": true,
+                "m.in_reply_to": { "event_id": root },
+            });
+        }
+        let path = format!(
+            "/rooms/{}/send/m.room.message/{}",
+            utf8_percent_encode(room_id, NON_ALPHANUMERIC),
+            uuid::Uuid::new_v4().simple()
+        );
+        let mut cmd = self.curl(&path);
+        cmd.args([
+            "-X",
+            "PUT",
+            "-H",
+            "Content-Type: application/json",
+            "--data-raw",
+            &content.to_string(),
+        ]);
+        let resp: SendResponse = self.call(cmd, Tool::Http)?;
+        Ok(resp.event_id)
+    }
+
+    /// Long-poll `/sync` for new messages in `room_id` after `since`, waiting
+    /// up to `timeout_secs`. Without `since`, returns the current position.
+    pub fn sync(
+        &self,
+        room_id: &str,
+        since: Option<&str>,
+        timeout_secs: u64,
+    ) -> Result<SyncResponse, MatrixError> {
+        let filter = json!({
+            "room": {
+                "rooms": [room_id],
+                "timeline": { "types": ["m.room.message"], "limit": 50 },
+                "state": { "types": [] },
+                "ephemeral": { "types": [] },
+                "account_data": { "types": [] },
+            },
+            "presence": { "types": [] },
+            "account_data": { "types": [] },
+        });
+        let mut cmd = self.curl("/sync");
+        cmd.args([
+            "--get",
+            "--data-urlencode",
+            &format!("filter={filter}"),
+            "--data",
+            &format!("timeout={}", timeout_secs * 1000),
+        ]);
+        if let Some(since) = since {
+            cmd.args(["--data-urlencode", &format!("since={since}")]);
+        }
+        self.call(cmd, Tool::LongPoll)
+    }
+}
+
+/// The configured client and room, or `None` if either is missing.
+fn configured() -> Option<(Client, &'static str)> {
+    Some((
+        Client::from_settings()?,
+        get_settings().matrix.room_id.as_deref()?,
+    ))
+}
+
+/// Send a plain message (confirmation/error) to the configured room.
+pub(crate) fn send_matrix(msg: &str) {
+    info!(msg, "sending Matrix message");
+    let Some((client, room_id)) = configured() else {
+        return;
+    };
+    if let Err(e) = client.send_text(room_id, msg, None) {
+        warn!(error = %e, "Matrix send failed");
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Away notification via Matrix — each notification roots its own thread
+// ---------------------------------------------------------------------------
+
+pub fn notify_away(turn: &TurnCompleted, _trace_id: &str, summary: &TurnSummary) -> NotifyOutcome {
+    let Some((client, room_id)) = configured() else {
+        warn!("Matrix homeserver, access_token or room_id not configured");
+        return NotifyOutcome::Skipped(SkipReason::NotConfigured);
+    };
+    let body = summary.get(&get_settings().ai.imessage_summary);
+    let (main_body, question) = split_body(body);
+    let message = format!(
+        "{} {} ({})",
+        pane_tag(turn),
+        main_body.trim(),
+        turn.main_context
+    );
+
+    let root = match client.send_text(room_id, &message, None) {
+        Ok(event_id) => event_id,
+        Err(e) => {
+            warn!(error = %e, "Matrix notification failed");
+            return NotifyOutcome::Failed {
+                channel: "matrix",
+                reason: e.to_string(),
+            };
+        }
+    };
+    info!(event_id = %root, "Matrix notification sent");
+    // Replies in the thread name the root; a rich reply quoting the question
+    // names the question. Both route back to this pane.
+    let mut reply_tokens = vec![root.clone()];
+    if let Some(q) = question {
+        match client.send_text(room_id, q, Some(&root)) {
+            Ok(event_id) => {
+                info!("Matrix question sent");
+                reply_tokens.push(event_id);
+            }
+            Err(e) => warn!(error = %e, "Matrix question failed"),
+        }
+    }
+
+    NotifyOutcome::Sent {
+        channel: "matrix",
+        source_agent: Some(AgentAddress::TmuxPane {
+            pane_id: turn.pane_id.clone(),
+            label: turn.pane_label.clone(),
+        }),
+        reply_tokens,
+    }
+}
+
+pub fn notify_digest_away(digest: &str) -> NotifyOutcome {
+    let Some((client, room_id)) = configured() else {
+        warn!("Matrix homeserver, access_token or room_id not configured");
+        return NotifyOutcome::Skipped(SkipReason::NotConfigured);
+    };
+    match client.send_text(room_id, digest, None) {
+        Ok(_) => {
+            info!("Matrix digest sent");
+            NotifyOutcome::Sent {
+                channel: "matrix",
+                source_agent: None,
+                reply_tokens: vec![],
+            }
+        }
+        Err(e) => NotifyOutcome::Failed {
+            channel: "matrix",
+            reason: e.to_string(),
+        },
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests — against a local homeserver stand-in
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::super::stand_in;
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn send_text_puts_threaded_event_into_room() {
+        crate::settings::init_settings_for_test();
+        let (base, rx) = stand_in::serve(vec![(200, r#"{"event_id":"$q1"}"#)]);
+
+        let client = Client::new(&base, "syt_abc");
+        let event_id = tokio::task::spawn_blocking(move || {
+            client.send_text("!room:example.org", "Should I push?", Some("$root"))
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(event_id, "$q1");
+        let req = rx.recv().unwrap();
+        assert!(
+            req.path.starts_with(
+                "/_matrix/client/v3/rooms/%21room%3Aexample%2Eorg/send/m.room.message/"
+            ),
+            "{}",
+            req.path
+        );
+        assert_eq!(req.header("Authorization"), Some("Bearer syt_abc"));
+        let body: Value = serde_json::from_str(&req.body).unwrap();
+        assert_eq!(body["body"], "Should I push?");
+        assert_eq!(body["m.relates_to"]["rel_type"], "m.thread");
+        assert_eq!(body["m.relates_to"]["event_id"], "$root");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sync_parses_timeline_and_api_errors() {
+        crate::settings::init_settings_for_test();
+        let (base, rx) = stand_in::serve(vec![
+            (
+                200,
+                r#"{"next_batch":"s2","rooms":{"join":{"!room:example.org":{"timeline":{"events":[{"event_id":"$r","sender":"@me:example.org","type":"m.room.message","origin_server_ts":1700000000000,"content":{"msgtype":"m.text","body":"ship it","m.relates_to":{"rel_type":"m.thread","event_id":"$root"}}}]}}}}}"#,
+            ),
+            (
+                401,
+                r#"{"errcode":"M_UNKNOWN_TOKEN","error":"Invalid access token"}"#,
+            ),
+        ]);
+
+        let client = Client::new(&base, "syt_abc");
+        let (sync, err) = tokio::task::spawn_blocking(move || {
+            (
+                client.sync("!room:example.org", Some("s1"), 0),
+                client.sync("!room:example.org", None, 0).unwrap_err(),
+            )
+        })
+        .await
+        .unwrap();
+
+        let sync = sync.unwrap();
+        assert_eq!(sync.next_batch, "s2");
+        let event = &sync.rooms.join["!room:example.org"].timeline.events[0];
+        assert_eq!(event.content.body.as_deref(), Some("ship it"));
+        assert_eq!(event.reply_token(), Some("$root"));
+        let req = rx.recv().unwrap();
+        assert!(req.path.contains("since=s1"), "{}", req.path);
+        assert!(req.path.contains("timeout=0"), "{}", req.path);
+        assert!(matches!(err, MatrixError::Api(d) if d == "M_UNKNOWN_TOKEN: Invalid access token"));
+    }
+}
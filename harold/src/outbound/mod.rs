@@ -1,13 +1,23 @@
+pub mod desktop;
+pub mod email;
 pub mod imessage;
+pub mod matrix;
+#[cfg(test)]
+pub(crate) mod stand_in;
+pub mod telegram;
 pub mod tts;
+pub mod webhook;
 
-use std::process::Command;
+use std::cell::OnceCell;
 
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::inbound::{AgentAddress, set_last_away_notification_source_agent};
-use crate::settings::get_settings;
-use crate::store::TurnCompleted;
+use crate::inbound::AgentAddress;
+use crate::presence;
+use crate::settings::{AtDeskChannel, AwayChannel, SummarizerBackend, get_settings};
+use crate::state;
+use crate::store::{Presence, SkipReason, TurnCompleted};
+use crate::summarizer::{SummaryRequest, extract, summarize};
 use crate::tmux;
 
 // ---------------------------------------------------------------------------
@@ -16,37 +26,157 @@ use crate::tmux;
 
 pub enum OutboundChannel {
     Tts,
+    DesktopNotification,
     IMessage,
+    Telegram,
+    Email,
+    Matrix,
+    Webhook,
+}
+
+/// What happened to a notification — recorded as an outcome event by the projector.
+#[derive(Debug)]
+pub enum NotifyOutcome {
+    /// Delivered. `source_agent` is set for away notifications (routing state);
+    /// `reply_tokens` are what a reply can name this notification by exactly.
+    Sent {
+        channel: &'static str,
+        source_agent: Option<AgentAddress>,
+        reply_tokens: Vec<String>,
+    },
+    Skipped(SkipReason),
+    Failed {
+        channel: &'static str,
+        reason: String,
+    },
 }
 
 impl OutboundChannel {
-    /// Send notification. Returns the source agent address if applicable (for routing state).
-    pub fn notify(&self, turn: &TurnCompleted, trace_id: &str) -> Option<AgentAddress> {
+    /// The channel used while the user is at the desk (`notify.at_desk_channel`).
+    pub fn at_desk() -> Self {
+        match get_settings().notify.at_desk_channel {
+            AtDeskChannel::Tts => OutboundChannel::Tts,
+            AtDeskChannel::Desktop => OutboundChannel::DesktopNotification,
+        }
+    }
+
+    /// The channel used while the user is away (`notify.away_channel`).
+    pub fn away() -> Self {
+        match get_settings().notify.away_channel {
+            AwayChannel::Imessage => OutboundChannel::IMessage,
+            AwayChannel::Telegram => OutboundChannel::Telegram,
+            AwayChannel::Email => OutboundChannel::Email,
+            AwayChannel::Matrix => OutboundChannel::Matrix,
+        }
+    }
+
+    /// Send notification. Text channels take their body from `summary`.
+    pub fn notify(
+        &self,
+        turn: &TurnCompleted,
+        trace_id: &str,
+        summary: &TurnSummary,
+    ) -> NotifyOutcome {
         match self {
-            OutboundChannel::Tts => {
-                tts::notify_at_desk(turn, trace_id);
-                None
+            OutboundChannel::Tts => tts::notify_at_desk(turn, trace_id),
+            OutboundChannel::DesktopNotification => {
+                desktop::notify_at_desk(turn, trace_id, summary)
             }
-            OutboundChannel::IMessage => imessage::notify_away(turn, trace_id),
+            OutboundChannel::IMessage => imessage::notify_away(turn, trace_id, summary),
+            OutboundChannel::Telegram => telegram::notify_away(turn, trace_id, summary),
+            OutboundChannel::Email => email::notify_away(turn, trace_id, summary),
+            OutboundChannel::Matrix => matrix::notify_away(turn, trace_id, summary),
+            OutboundChannel::Webhook => webhook::notify(turn, trace_id, summary),
         }
     }
 }
 
 // ---------------------------------------------------------------------------
-// Screen lock detection
+// Turn summaries for text notifications
 // ---------------------------------------------------------------------------
 
-pub fn is_screen_locked() -> bool {
-    let result = Command::new("bash")
-        .args([
-            "-c",
-            "ioreg -n Root -d1 -a | plutil -extract IOConsoleLocked raw -",
-        ])
-        .output();
+/// Cap `assistant_message` to 280 chars and flatten newlines into spaces.
+fn truncate_body(assistant_message: &str) -> String {
+    assistant_message
+        .chars()
+        .take(280)
+        .collect::<String>()
+        .replace('\n', " ")
+}
+
+/// How an away notification names its pane: `#3 [label]`, so a reply can
+/// start with `#3`; just `[label]` before the pane has a handle.
+pub(crate) fn pane_tag(turn: &TurnCompleted) -> String {
+    match state::pane_handle(&turn.pane_id) {
+        Some((handle, _)) => format!("#{handle} [{}]", turn.pane_label),
+        None => format!("[{}]", turn.pane_label),
+    }
+}
 
-    match result {
-        Ok(out) => String::from_utf8_lossy(&out.stdout).trim() == "true",
-        Err(_) => false,
+/// Summarise a turn for a notification with the backend `chain`. Falls back to
+/// the offline extractive summary if every backend declines, so a missing or
+/// failing model never sends raw preamble.
+pub(crate) fn summarise_turn(
+    chain: &[SummarizerBackend],
+    assistant_message: &str,
+    last_user_prompt: &str,
+) -> String {
+    let safe_msg = assistant_message
+        .replace("</message>", "")
+        .replace("</prompt>", "");
+    let safe_prompt = last_user_prompt
+        .replace("</prompt>", "")
+        .replace("</message>", "");
+    let prompt = format!(
+        "USER ASKED:\n<prompt>\n{safe_prompt}\n</prompt>\n\n\
+         ASSISTANT REPLIED:\n<message>\n{safe_msg}\n</message>\n\n\
+         Write 2-3 plain sentences summarising what was done and the outcome.\n\
+         Preserve any question the assistant asked.\n\
+         No code, no markdown, no jargon. Keep it under 280 characters."
+    );
+    let req = SummaryRequest {
+        system_prompt: "You are writing a phone notification summary.",
+        prompt: &prompt,
+        source: assistant_message,
+        max_chars: 280,
+        max_tokens: 120,
+    };
+
+    match summarize(chain, &req) {
+        Some(summary) => truncate_body(&summary),
+        None => {
+            warn!("summarise_turn: no summariser available, falling back to extraction");
+            extract(assistant_message, 280)
+        }
+    }
+}
+
+/// A turn's text summary, produced at most once per notification so the
+/// primary channel and the webhook never run the model twice or report
+/// different text for the same turn.
+pub struct TurnSummary<'a> {
+    turn: &'a TurnCompleted,
+    text: OnceCell<String>,
+}
+
+impl<'a> TurnSummary<'a> {
+    pub fn new(turn: &'a TurnCompleted) -> Self {
+        Self {
+            turn,
+            text: OnceCell::new(),
+        }
+    }
+
+    /// The summary, produced with `chain` by the first channel to ask; later
+    /// channels get the same text whatever their own chain.
+    pub(crate) fn get(&self, chain: &[SummarizerBackend]) -> &str {
+        self.text.get_or_init(|| {
+            summarise_turn(
+                chain,
+                &self.turn.assistant_message,
+                &self.turn.last_user_prompt,
+            )
+        })
     }
 }
 
@@ -54,40 +184,152 @@ pub fn is_screen_locked() -> bool {
 // Notify orchestrator
 // ---------------------------------------------------------------------------
 
-pub fn notify(turn: &TurnCompleted, trace_id: &str) {
+/// Notify the user of a completed turn: one outcome for the presence-selected
+/// channel, plus one for webhooks when any are configured and the turn was not
+/// skipped. Both share one summary of the turn.
+pub fn notify(turn: &TurnCompleted, trace_id: &str) -> Vec<NotifyOutcome> {
+    let summary = TurnSummary::new(turn);
+    let primary = notify_primary(turn, trace_id, &summary);
+    let mut outcomes = vec![primary];
+    if !matches!(outcomes[0], NotifyOutcome::Skipped(_)) && !get_settings().webhook.urls.is_empty()
+    {
+        outcomes.push(OutboundChannel::Webhook.notify(turn, trace_id, &summary));
+    }
+    outcomes
+}
+
+fn notify_primary(turn: &TurnCompleted, trace_id: &str, summary: &TurnSummary) -> NotifyOutcome {
     let cfg = get_settings();
-    let screen_locked = is_screen_locked();
+    let away = presence::detect() == Presence::Away;
 
     // Session-level skip: if completing pane's session has an attached client AND the
-    // screen is not locked, skip entirely.  When the screen is locked the user is away
-    // from the desk, so we must still notify even though tmux is attached.
-    if cfg.notify.skip_if_session_active
-        && !screen_locked
-        && tmux::is_session_attached(&turn.pane_id)
-    {
-        info!("notification skipped (session is active, screen unlocked)");
-        return;
+    // user is at the desk, skip entirely.  When the user is away (screen locked, idle,
+    // manual override) we must still notify even though tmux is attached.
+    if cfg.notify.skip_if_session_active && !away && tmux::is_session_attached(&turn.pane_id) {
+        info!("notification skipped (session is active, user at desk)");
+        return NotifyOutcome::Skipped(SkipReason::SessionActive);
     }
 
     // Pane-level skip: skip only when the completing pane is the active pane
-    // AND the screen is not locked (user is at desk looking at it).
-    // If screen is locked, always notify even if pane matches.
+    // AND the user is at the desk looking at it.
+    // If the user is away, always notify even if pane matches.
     if cfg.notify.skip_if_pane_active
-        && !screen_locked
+        && !away
         && let Some(active_pane) = tmux::active_pane_in_session(&turn.pane_id)
         && active_pane == turn.pane_id
     {
-        info!("notification skipped (pane is active and screen unlocked)");
-        return;
+        info!("notification skipped (pane is active and user at desk)");
+        return NotifyOutcome::Skipped(SkipReason::PaneActive);
     }
 
-    let channel = if screen_locked {
-        OutboundChannel::IMessage
+    let channel = if away {
+        OutboundChannel::away()
     } else {
-        OutboundChannel::Tts
+        OutboundChannel::at_desk()
     };
 
-    if let Some(source_agent) = channel.notify(turn, trace_id) {
-        set_last_away_notification_source_agent(source_agent);
+    channel.notify(turn, trace_id, summary)
+}
+
+/// Send a plain text (routing confirmation or error) on the away channel, where
+/// replies come from.
+pub(crate) fn send_away_text(msg: &str) {
+    match OutboundChannel::away() {
+        OutboundChannel::Telegram => telegram::send_telegram(msg),
+        OutboundChannel::Email => email::send_email(msg),
+        OutboundChannel::Matrix => matrix::send_matrix(msg),
+        _ => imessage::send_imessage(msg),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Offline digest — stale turns folded into one notification
+// ---------------------------------------------------------------------------
+
+/// One line summarising turns that completed while Harold was not running,
+/// e.g. "While Harold was offline, 3 turns completed: work:0.0 (2), home:0.1".
+pub(crate) fn offline_digest(pane_labels: &[String]) -> String {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for label in pane_labels {
+        match counts.iter_mut().find(|(l, _)| *l == label) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((label, 1)),
+        }
+    }
+    let panes = counts
+        .iter()
+        .map(|(label, n)| {
+            if *n > 1 {
+                format!("{label} ({n})")
+            } else {
+                label.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let turns = if pane_labels.len() == 1 {
+        "1 turn".to_string()
+    } else {
+        format!("{} turns", pane_labels.len())
+    };
+    format!("While Harold was offline, {turns} completed: {panes}")
+}
+
+/// Send the offline digest on whichever channel `notify()` would pick right now.
+pub fn notify_digest(pane_labels: &[String]) -> NotifyOutcome {
+    let digest = offline_digest(pane_labels);
+    if presence::detect() == Presence::Away {
+        return match OutboundChannel::away() {
+            OutboundChannel::Telegram => telegram::notify_digest_away(&digest),
+            OutboundChannel::Email => email::notify_digest_away(&digest),
+            OutboundChannel::Matrix => matrix::notify_digest_away(&digest),
+            _ => imessage::notify_digest_away(&digest),
+        };
+    }
+    match OutboundChannel::at_desk() {
+        OutboundChannel::DesktopNotification => desktop::notify_digest_at_desk(&digest),
+        _ => tts::notify_digest_at_desk(&digest),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{offline_digest, truncate_body};
+
+    #[test]
+    fn offline_digest_counts_repeated_panes() {
+        let labels = vec!["work:0.0".into(), "home:0.1".into(), "work:0.0".into()];
+        assert_eq!(
+            offline_digest(&labels),
+            "While Harold was offline, 3 turns completed: work:0.0 (2), home:0.1"
+        );
+    }
+
+    #[test]
+    fn offline_digest_single_turn() {
+        assert_eq!(
+            offline_digest(&["work:0.0".into()]),
+            "While Harold was offline, 1 turn completed: work:0.0"
+        );
+    }
+
+    #[test]
+    fn truncate_body_caps_at_280_chars_and_flattens_newlines() {
+        let short = "Hello world.\nDone.";
+        assert_eq!(truncate_body(short), "Hello world. Done.");
+
+        let long: String = "x".repeat(300);
+        let result = truncate_body(&long);
+        assert_eq!(result.len(), 280);
+
+        // Multi-byte: caps at 280 *characters*, not bytes.
+        let emoji_long: String = "\u{1F600}".repeat(300);
+        let result = truncate_body(&emoji_long);
+        assert_eq!(result.chars().count(), 280);
+        assert!(result.len() > 280);
     }
 }
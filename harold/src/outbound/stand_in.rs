@@ -0,0 +1,192 @@
+//! Minimal HTTP, SMTP and IMAP servers for tests of the curl-based channels.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+
+/// One request as the stand-in received it.
+pub(crate) struct Request {
+    pub path: String,
+    pub headers: Vec<String>,
+    pub body: String,
+}
+
+impl Request {
+    /// Value of header `name` (case-sensitive, as curl sends it).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find_map(|h| h.strip_prefix(name)?.strip_prefix(": "))
+    }
+}
+
+/// Serve one connection per entry of `responses` (status, JSON body), in order.
+/// Returns the base URL and a channel yielding each request received.
+pub(crate) fn serve(responses: Vec<(u16, &'static str)>) -> (String, mpsc::Receiver<Request>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for (status, body) in responses {
+            let Ok((stream, _)) = listener.accept() else {
+                return;
+            };
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let path = request_line
+                .split_whitespace()
+                .nth(1)
+                .unwrap_or_default()
+                .to_string();
+            let mut headers = Vec::new();
+            let mut len = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_string();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(v) = line.to_lowercase().strip_prefix("content-length: ") {
+                    len = v.parse().unwrap();
+                }
+                headers.push(line);
+            }
+            let mut buf = vec![0; len];
+            reader.read_exact(&mut buf).unwrap();
+            let _ = tx.send(Request {
+                path,
+                headers,
+                body: String::from_utf8(buf).unwrap(),
+            });
+            let reply = format!(
+                "HTTP/1.1 {status} X\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            reader.get_mut().write_all(reply.as_bytes()).unwrap();
+        }
+    });
+    (base, rx)
+}
+
+fn send(reader: &mut BufReader<TcpStream>, s: &str) {
+    reader.get_mut().write_all(s.as_bytes()).unwrap();
+}
+
+/// SMTP server for one session. Yields the envelope recipients and the message.
+pub(crate) fn smtp() -> (String, mpsc::Receiver<(Vec<String>, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("smtp://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        send(&mut reader, "220 stand-in ESMTP\r\n");
+        let mut rcpts = Vec::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                return;
+            }
+            let upper = line.to_uppercase();
+            if upper.starts_with("EHLO") || upper.starts_with("HELO") {
+                send(&mut reader, "250 stand-in\r\n");
+            } else if let Some(to) = upper.strip_prefix("RCPT TO:") {
+                rcpts.push(to.trim().trim_matches(['<', '>']).to_lowercase());
+                send(&mut reader, "250 OK\r\n");
+            } else if upper.starts_with("DATA") {
+                send(&mut reader, "354 go ahead\r\n");
+                let mut data = String::new();
+                loop {
+                    let mut l = String::new();
+                    reader.read_line(&mut l).unwrap();
+                    if l == ".\r\n" {
+                        break;
+                    }
+                    data.push_str(&l);
+                }
+                let _ = tx.send((std::mem::take(&mut rcpts), data));
+                send(&mut reader, "250 queued\r\n");
+            } else if upper.starts_with("QUIT") {
+                send(&mut reader, "221 bye\r\n");
+                return;
+            } else {
+                send(&mut reader, "250 OK\r\n");
+            }
+        }
+    });
+    (base, rx)
+}
+
+/// IMAP server holding `messages` (uid, raw RFC 822) in one folder. Answers
+/// `UID SEARCH UID n:*` and `UID FETCH n BODY[]` for up to `sessions` logins.
+pub(crate) fn imap(messages: Vec<(u32, &'static str)>, sessions: usize) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("imap://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for _ in 0..sessions {
+            let Ok((stream, _)) = listener.accept() else {
+                return;
+            };
+            let mut reader = BufReader::new(stream);
+            send(&mut reader, "* OK stand-in IMAP4rev1\r\n");
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                let (tag, cmd) = line.trim_end().split_once(' ').unwrap();
+                let upper = cmd.to_uppercase();
+                if upper.starts_with("CAPABILITY") {
+                    send(
+                        &mut reader,
+                        &format!("* CAPABILITY IMAP4rev1\r\n{tag} OK\r\n"),
+                    );
+                } else if upper.starts_with("SELECT") || upper.starts_with("EXAMINE") {
+                    let exists = messages.len();
+                    send(
+                        &mut reader,
+                        &format!("* {exists} EXISTS\r\n{tag} OK [READ-WRITE]\r\n"),
+                    );
+                } else if let Some(range) = upper.strip_prefix("UID SEARCH UID ") {
+                    // `n:*` always includes the highest uid, as real servers do.
+                    let max = messages.iter().map(|m| m.0).max().unwrap_or(0);
+                    let from: u32 = match range.split_once(':') {
+                        Some((n, _)) => n.parse().unwrap(),
+                        None => max,
+                    };
+                    let uids: Vec<String> = messages
+                        .iter()
+                        .filter(|m| m.0 >= from || m.0 == max)
+                        .map(|m| m.0.to_string())
+                        .collect();
+                    send(
+                        &mut reader,
+                        &format!("* SEARCH {}\r\n{tag} OK\r\n", uids.join(" ")),
+                    );
+                } else if let Some(rest) = upper.strip_prefix("UID FETCH ") {
+                    let uid: u32 = rest.split(' ').next().unwrap().parse().unwrap();
+                    if let Some((_, msg)) = messages.iter().find(|m| m.0 == uid) {
+                        let msg = msg.replace('\n', "\r\n");
+                        send(
+                            &mut reader,
+                            &format!(
+                                "* 1 FETCH (UID {uid} BODY[] {{{}}}\r\n{msg})\r\n",
+                                msg.len()
+                            ),
+                        );
+                    }
+                    send(&mut reader, &format!("{tag} OK\r\n"));
+                } else if upper.starts_with("LOGOUT") {
+                    send(&mut reader, &format!("* BYE\r\n{tag} OK\r\n"));
+                    break;
+                } else {
+                    send(&mut reader, &format!("{tag} OK\r\n"));
+                }
+            }
+        }
+    });
+    base
+}
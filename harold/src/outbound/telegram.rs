@@ -0,0 +1,279 @@
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use tokio::process::Command;
+use tracing::{info, warn};
+
+use super::imessage::split_body;
+use super::{NotifyOutcome, TurnSummary, pane_tag};
+use crate::inbound::AgentAddress;
+use crate::proc::{ProcError, Tool, run_blocking_with_input};
+use crate::settings::get_settings;
+use crate::store::{SkipReason, TurnCompleted};
+use crate::util::curl_config;
+
+// ---------------------------------------------------------------------------
+// Bot API client — requests go through curl, like every other tool
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, thiserror::Error)]
+pub enum TelegramError {
+    #[error(transparent)]
+    Proc(#[from] ProcError),
+    #[error("curl failed: {0}")]
+    Curl(String),
+    #[error("Telegram API error: {0}")]
+    Api(String),
+    #[error("unexpected Telegram response: {0}")]
+    Malformed(#[from] serde_json::Error),
+}
+
+/// Every Bot API reply is wrapped in `{"ok": …, "result": …}`.
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Update {
+    pub update_id: i64,
+    pub message: Option<Message>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Message {
+    pub chat: Chat,
+    /// Unix seconds.
+    pub date: i64,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Chat {
+    pub id: i64,
+}
+
+/// A bot token and the API it talks to (`telegram.api_base`).
+#[derive(Debug, Clone)]
+pub(crate) struct Bot {
+    api_base: String,
+    token: String,
+}
+
+impl Bot {
+    pub fn new(api_base: &str, token: &str) -> Self {
+        Self {
+            api_base: api_base.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    /// The configured bot, if `telegram.bot_token` is set.
+    pub fn from_settings() -> Option<Self> {
+        let cfg = &get_settings().telegram;
+        Some(Self::new(&cfg.api_base, cfg.bot_token.as_deref()?))
+    }
+
+    fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &Value,
+        tool: Tool,
+    ) -> Result<T, TelegramError> {
+        // The token is part of the URL, so the URL goes in the config.
+        let url = format!("{}/bot{}/{method}", self.api_base, self.token);
+        let mut cmd = Command::new("curl");
+        cmd.args([
+            "--silent",
+            "--show-error",
+            "-K",
+            "-",
+            "-X",
+            "POST",
+            "-H",
+            "Content-Type: application/json",
+            "--data-raw",
+            &params.to_string(),
+        ]);
+        let out = run_blocking_with_input(cmd, tool, curl_config(&[("url", &url)]))?;
+        if !out.status.success() {
+            return Err(TelegramError::Curl(
+                String::from_utf8_lossy(&out.stderr).trim().to_string(),
+            ));
+        }
+        let resp: ApiResponse<T> = serde_json::from_slice(&out.stdout)?;
+        match resp.result {
+            Some(result) if resp.ok => Ok(result),
+            _ => Err(TelegramError::Api(
+                resp.description.unwrap_or_else(|| "no result".into()),
+            )),
+        }
+    }
+
+    pub fn send_message(&self, chat_id: i64, text: &str) -> Result<(), TelegramError> {
+        let _: Value = self.call(
+            "sendMessage",
+            &json!({ "chat_id": chat_id, "text": text }),
+            Tool::Http,
+        )?;
+        Ok(())
+    }
+
+    /// Long-poll for updates from `offset` on, waiting up to `timeout_secs`.
+    pub fn get_updates(
+        &self,
+        offset: i64,
+        timeout_secs: u64,
+    ) -> Result<Vec<Update>, TelegramError> {
+        self.call(
+            "getUpdates",
+            &json!({
+                "offset": offset,
+                "timeout": timeout_secs,
+                "allowed_updates": ["message"],
+            }),
+            Tool::LongPoll,
+        )
+    }
+}
+
+/// The configured bot and chat, or `None` if either is missing.
+fn configured() -> Option<(Bot, i64)> {
+    Some((Bot::from_settings()?, get_settings().telegram.chat_id?))
+}
+
+/// Send a plain message (confirmation/error) to the configured chat.
+pub(crate) fn send_telegram(msg: &str) {
+    info!(msg, "sending Telegram message");
+    let Some((bot, chat_id)) = configured() else {
+        return;
+    };
+    if let Err(e) = bot.send_message(chat_id, msg) {
+        warn!(error = %e, "Telegram send failed");
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Away notification via Telegram — reports the source agent address
+// ---------------------------------------------------------------------------
+
+pub fn notify_away(turn: &TurnCompleted, _trace_id: &str, summary: &TurnSummary) -> NotifyOutcome {
+    let Some((bot, chat_id)) = configured() else {
+        warn!("Telegram bot_token or chat_id not configured");
+        return NotifyOutcome::Skipped(SkipReason::NotConfigured);
+    };
+    let body = summary.get(&get_settings().ai.imessage_summary);
+    let (main_body, question) = split_body(body);
+    let message = format!(
+        "{} {} ({})",
+        pane_tag(turn),
+        main_body.trim(),
+        turn.main_context
+    );
+
+    if let Err(e) = bot.send_message(chat_id, &message) {
+        warn!(error = %e, "Telegram notification failed");
+        return NotifyOutcome::Failed {
+            channel: "telegram",
+            reason: e.to_string(),
+        };
+    }
+    info!("Telegram notification sent");
+    if let Some(q) = question {
+        match bot.send_message(chat_id, q) {
+            Ok(()) => info!("Telegram question sent"),
+            Err(e) => warn!(error = %e, "Telegram question failed"),
+        }
+    }
+
+    NotifyOutcome::Sent {
+        channel: "telegram",
+        source_agent: Some(AgentAddress::TmuxPane {
+            pane_id: turn.pane_id.clone(),
+            label: turn.pane_label.clone(),
+        }),
+        reply_tokens: vec![],
+    }
+}
+
+pub fn notify_digest_away(digest: &str) -> NotifyOutcome {
+    let Some((bot, chat_id)) = configured() else {
+        warn!("Telegram bot_token or chat_id not configured");
+        return NotifyOutcome::Skipped(SkipReason::NotConfigured);
+    };
+    match bot.send_message(chat_id, digest) {
+        Ok(()) => {
+            info!("Telegram digest sent");
+            NotifyOutcome::Sent {
+                channel: "telegram",
+                source_agent: None,
+                reply_tokens: vec![],
+            }
+        }
+        Err(e) => NotifyOutcome::Failed {
+            channel: "telegram",
+            reason: e.to_string(),
+        },
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests — against a local Bot API stand-in
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::super::stand_in;
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn send_message_posts_chat_id_and_text() {
+        crate::settings::init_settings_for_test();
+        let (base, rx) = stand_in::serve(vec![(200, r#"{"ok":true,"result":{"message_id":1}}"#)]);
+
+        let bot = Bot::new(&base, "123:abc");
+        tokio::task::spawn_blocking(move || bot.send_message(42, "[harold:0.1] Done (main)"))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let req = rx.recv().unwrap();
+        assert_eq!(req.path, "/bot123:abc/sendMessage");
+        let body: Value = serde_json::from_str(&req.body).unwrap();
+        assert_eq!(body["chat_id"], 42);
+        assert_eq!(body["text"], "[harold:0.1] Done (main)");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn get_updates_parses_messages_and_api_errors() {
+        crate::settings::init_settings_for_test();
+        let (base, rx) = stand_in::serve(vec![
+            (
+                200,
+                r#"{"ok":true,"result":[{"update_id":7,"message":{"message_id":3,"date":1700000000,"chat":{"id":42,"type":"private"},"text":"[harold] ship it"}}]}"#,
+            ),
+            (
+                401,
+                r#"{"ok":false,"error_code":401,"description":"Unauthorized"}"#,
+            ),
+        ]);
+
+        let bot = Bot::new(&base, "123:abc");
+        let (updates, err) = tokio::task::spawn_blocking(move || {
+            (bot.get_updates(5, 0), bot.get_updates(8, 0).unwrap_err())
+        })
+        .await
+        .unwrap();
+
+        let updates = updates.unwrap();
+        assert_eq!(updates[0].update_id, 7);
+        let msg = updates[0].message.as_ref().unwrap();
+        assert_eq!(msg.chat.id, 42);
+        assert_eq!(msg.text.as_deref(), Some("[harold] ship it"));
+        let body: Value = serde_json::from_str(&rx.recv().unwrap().body).unwrap();
+        assert_eq!(body["offset"], 5);
+        assert!(matches!(err, TelegramError::Api(d) if d == "Unauthorized"));
+    }
+}
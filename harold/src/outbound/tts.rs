@@ -1,91 +1,11 @@
-use std::process::Command;
-
+use tokio::process::Command;
 use tracing::{info, warn};
 
+use super::NotifyOutcome;
+use crate::proc::{Tool, run_blocking};
 use crate::settings::get_settings;
 use crate::store::TurnCompleted;
-
-fn run_local_model(system_prompt: &str, prompt: &str, max_tokens: u32) -> Option<String> {
-    let cfg = get_settings();
-    let model = cfg.ai.local_model.as_deref()?;
-    let model_dir = cfg.ai.local_model_dir.as_deref()?;
-
-    let out = Command::new("uv")
-        .args([
-            "run",
-            "mlx_lm.generate",
-            "--model",
-            model,
-            "--system-prompt",
-            system_prompt,
-            "--prompt",
-            prompt,
-            "--max-tokens",
-            &max_tokens.to_string(),
-        ])
-        .current_dir(model_dir)
-        .output()
-        .ok()?;
-
-    if !out.status.success() {
-        return None;
-    }
-
-    let output = String::from_utf8_lossy(&out.stdout).trim().to_string();
-    if output.is_empty() {
-        return None;
-    }
-
-    // Extract content between ========== markers (mlx_lm output format)
-    if output.contains("==========") {
-        let mut in_content = false;
-        let mut lines: Vec<&str> = Vec::new();
-        for line in output.lines() {
-            if line.trim() == "==========" {
-                if in_content {
-                    break;
-                }
-                in_content = true;
-            } else if in_content {
-                lines.push(line.trim());
-            }
-        }
-        let text = lines
-            .join(" ")
-            .trim()
-            .trim_matches('"')
-            .trim_matches('\'')
-            .to_string();
-        // Strip <think>...</think> blocks (reasoning models)
-        let text = regex_strip_think(&text);
-        if text.is_empty() { None } else { Some(text) }
-    } else {
-        let text = output.trim_matches('"').trim_matches('\'');
-        let text = regex_strip_think(text);
-        if text.is_empty() {
-            None
-        } else {
-            Some(text.chars().take(200).collect())
-        }
-    }
-}
-
-pub(crate) fn regex_strip_think(text: &str) -> String {
-    // Remove <think>...</think> blocks emitted by reasoning models like Qwen3.
-    let mut result = String::with_capacity(text.len());
-    let mut rest = text;
-    while let Some(start) = rest.find("<think>") {
-        result.push_str(&rest[..start]);
-        if let Some(end) = rest.find("</think>") {
-            rest = rest[end + "</think>".len()..].trim_start();
-        } else {
-            rest = "";
-            break;
-        }
-    }
-    result.push_str(rest);
-    result.trim().to_string()
-}
+use crate::summarizer::{SummaryRequest, summarize};
 
 fn build_short_summary(turn: &TurnCompleted) -> String {
     let system_prompt = "You are a notification assistant. Given a user's last request, \
@@ -96,15 +16,18 @@ fn build_short_summary(turn: &TurnCompleted) -> String {
         "User's last request: {}\n\nWrite a 3-8 word summary of what was done:",
         turn.last_user_prompt.chars().take(500).collect::<String>(),
     );
-    run_local_model(system_prompt, &prompt, 20).unwrap_or_else(|| "Work complete".into())
+    let req = SummaryRequest {
+        system_prompt,
+        prompt: &prompt,
+        source: &turn.last_user_prompt,
+        max_chars: 200,
+        max_tokens: 20,
+    };
+    summarize(&get_settings().ai.tts_summary, &req).unwrap_or_else(|| "Work complete".into())
 }
 
-pub fn notify_at_desk(turn: &TurnCompleted, _trace_id: &str) {
-    let summary = build_short_summary(turn);
-    let message = format!(
-        "{} on {} and waiting for further instructions",
-        summary, turn.main_context
-    );
+/// Speak `message` with the configured TTS command.
+fn speak(message: &str) -> NotifyOutcome {
     let tts = &get_settings().tts;
     let mut cmd = Command::new(&tts.command);
     if let Some(extra_args) = &tts.args {
@@ -113,8 +36,43 @@ pub fn notify_at_desk(turn: &TurnCompleted, _trace_id: &str) {
     if let Some(voice) = &tts.voice {
         cmd.args(["-v", voice]);
     }
-    match cmd.arg(&message).status() {
-        Ok(_) => info!("TTS notification sent"),
-        Err(e) => warn!(error = %e, "TTS failed"),
+    cmd.arg(message);
+    match run_blocking(cmd, Tool::Tts) {
+        Ok(out) if !out.status.success() => {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            warn!(status = %out.status, stderr = %stderr.trim(), "TTS failed");
+            NotifyOutcome::Failed {
+                channel: "tts",
+                reason: format!("{} exited with {}", tts.command, out.status),
+            }
+        }
+        Ok(_) => {
+            info!("TTS notification sent");
+            NotifyOutcome::Sent {
+                channel: "tts",
+                source_agent: None,
+                reply_tokens: vec![],
+            }
+        }
+        Err(e) => {
+            warn!(error = %e, "TTS failed");
+            NotifyOutcome::Failed {
+                channel: "tts",
+                reason: e.to_string(),
+            }
+        }
     }
 }
+
+pub fn notify_at_desk(turn: &TurnCompleted, _trace_id: &str) -> NotifyOutcome {
+    let summary = build_short_summary(turn);
+    let message = format!(
+        "{} on {} and waiting for further instructions",
+        summary, turn.main_context
+    );
+    speak(&message)
+}
+
+pub fn notify_digest_at_desk(digest: &str) -> NotifyOutcome {
+    speak(digest)
+}
@@ -0,0 +1,270 @@
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+use super::imessage::split_body;
+use super::{NotifyOutcome, TurnSummary};
+use crate::proc::{Tool, run_blocking, sleep_blocking};
+use crate::settings::{WebhookSettings, get_settings};
+use crate::store::TurnCompleted;
+use crate::util::unix_ms;
+
+// ---------------------------------------------------------------------------
+// Payload
+// ---------------------------------------------------------------------------
+
+/// JSON document POSTed to every `webhook.urls` entry.
+#[derive(Debug, Serialize)]
+pub(crate) struct WebhookPayload<'a> {
+    pub trace_id: &'a str,
+    pub pane_id: &'a str,
+    pub pane_label: &'a str,
+    pub main_context: &'a str,
+    pub summary: &'a str,
+    pub question: Option<&'a str>,
+}
+
+/// Hex HMAC-SHA256 of `"<timestamp>.<body>"` — the `X-Harold-Signature` value.
+pub(crate) fn sign(secret: &str, timestamp: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Delivery — curl with retry and exponential backoff
+// ---------------------------------------------------------------------------
+
+enum Attempt {
+    Delivered,
+    /// Network error, 5xx or 429 — worth another try.
+    Retry(String),
+    /// Other 4xx — the receiver rejected it; retrying won't help.
+    Rejected(String),
+}
+
+fn post_once(url: &str, body: &str, secret: &str) -> Attempt {
+    let timestamp = unix_ms(time::OffsetDateTime::now_utc()).to_string();
+    let signature = sign(secret, &timestamp, body);
+    let mut cmd = Command::new("curl");
+    cmd.args([
+        "--silent",
+        "--show-error",
+        "--output",
+        "/dev/null",
+        "--write-out",
+        "%{http_code}",
+        "-X",
+        "POST",
+        "-H",
+        "Content-Type: application/json",
+        "-H",
+        &format!("X-Harold-Timestamp: {timestamp}"),
+        "-H",
+        &format!("X-Harold-Signature: sha256={signature}"),
+        "--data-raw",
+        body,
+        url,
+    ]);
+    let out = match run_blocking(cmd, Tool::Http) {
+        Ok(out) => out,
+        Err(e) => return Attempt::Retry(e.to_string()),
+    };
+    let code: u16 = String::from_utf8_lossy(&out.stdout)
+        .trim()
+        .parse()
+        .unwrap_or(0);
+    match code {
+        200..=299 => Attempt::Delivered,
+        0 => Attempt::Retry(String::from_utf8_lossy(&out.stderr).trim().to_string()),
+        429 | 500..=599 => Attempt::Retry(format!("HTTP {code}")),
+        _ => Attempt::Rejected(format!("HTTP {code}")),
+    }
+}
+
+/// Longest wait between attempts, whatever `initial_backoff_ms` and the
+/// number of attempts, so retries never hold the pane's lane for long.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The wait before retry `retry` (1-based): doubling from `initial`, capped.
+fn backoff(initial: Duration, retry: u32) -> Duration {
+    initial
+        .saturating_mul(2u32.saturating_pow(retry - 1))
+        .min(MAX_BACKOFF)
+}
+
+/// POST `body` to `url`, retrying with capped exponential backoff until
+/// Harold shuts down. Blocking.
+pub(crate) fn deliver(
+    url: &str,
+    body: &str,
+    cfg: &WebhookSettings,
+    secret: &str,
+) -> Result<(), String> {
+    let initial = Duration::from_millis(cfg.initial_backoff_ms);
+    let mut last_error = String::new();
+    for attempt in 1..=cfg.max_attempts.max(1) {
+        match post_once(url, body, secret) {
+            Attempt::Delivered => {
+                info!(url, attempt, "webhook delivered");
+                return Ok(());
+            }
+            Attempt::Rejected(reason) => {
+                warn!(url, %reason, "webhook rejected");
+                return Err(reason);
+            }
+            Attempt::Retry(reason) => {
+                warn!(url, attempt, %reason, "webhook attempt failed");
+                last_error = reason;
+                if attempt < cfg.max_attempts && !sleep_blocking(backoff(initial, attempt)) {
+                    warn!(url, "webhook retries stopped by shutdown");
+                    break;
+                }
+            }
+        }
+    }
+    Err(last_error)
+}
+
+// ---------------------------------------------------------------------------
+// Channel entry point
+// ---------------------------------------------------------------------------
+
+/// Post the turn to every webhook. Reuses the primary channel's summary when
+/// it made one; `ai.webhook_summary` only applies when it did not (TTS).
+pub fn notify(turn: &TurnCompleted, trace_id: &str, summary: &TurnSummary) -> NotifyOutcome {
+    let cfg = &get_settings().webhook;
+    let Some(secret) = cfg.secret.as_deref() else {
+        // validate() rejects urls without a secret; nothing to do when neither is set.
+        return NotifyOutcome::Skipped(crate::store::SkipReason::NotConfigured);
+    };
+    let summary = summary.get(&get_settings().ai.webhook_summary);
+    let (main_body, question) = split_body(summary);
+    let payload = WebhookPayload {
+        trace_id,
+        pane_id: &turn.pane_id,
+        pane_label: &turn.pane_label,
+        main_context: &turn.main_context,
+        summary: main_body,
+        question,
+    };
+    let body = serde_json::to_string(&payload).expect("payload serialises");
+
+    let failures: Vec<String> = cfg
+        .urls
+        .iter()
+        .filter_map(|url| {
+            deliver(url, &body, cfg, secret)
+                .err()
+                .map(|e| format!("{url}: {e}"))
+        })
+        .collect();
+    if failures.is_empty() {
+        NotifyOutcome::Sent {
+            channel: "webhook",
+            source_agent: None,
+            reply_tokens: vec![],
+        }
+    } else {
+        NotifyOutcome::Failed {
+            channel: "webhook",
+            reason: failures.join("; "),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests — against a local HTTP stand-in
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::super::stand_in;
+    use super::*;
+
+    #[test]
+    fn sign_is_hmac_sha256_of_timestamp_dot_body() {
+        // python3 -c 'import hmac,hashlib; print(hmac.new(b"s3cret",
+        //   b"1700000000000.{\"pane_id\":\"%1\"}", hashlib.sha256).hexdigest())'
+        assert_eq!(
+            sign("s3cret", "1700000000000", r#"{"pane_id":"%1"}"#),
+            "e24d7ec50383615ee762f3d8825c422ea059e53922b1aafe8585ae11f8445f37"
+        );
+        assert_ne!(
+            sign("s3cret", "1700000000001", r#"{"pane_id":"%1"}"#),
+            sign("s3cret", "1700000000000", r#"{"pane_id":"%1"}"#),
+            "timestamp is part of the signed message"
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let initial = Duration::from_millis(500);
+        assert_eq!(backoff(initial, 1), initial);
+        assert_eq!(backoff(initial, 3), Duration::from_secs(2));
+        assert_eq!(backoff(initial, 10), MAX_BACKOFF);
+        assert_eq!(backoff(initial, 64), MAX_BACKOFF);
+    }
+
+    fn settings(max_attempts: u32) -> WebhookSettings {
+        WebhookSettings {
+            urls: vec![],
+            secret: Some("s3cret".into()),
+            max_attempts,
+            initial_backoff_ms: 10,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn deliver_signs_and_retries_server_errors() {
+        crate::settings::init_settings_for_test();
+        let (base, rx) = stand_in::serve(vec![(503, ""), (200, "")]);
+        let url = format!("{base}/hook");
+        let body = r#"{"pane_id":"%1"}"#.to_string();
+
+        let result =
+            tokio::task::spawn_blocking(move || deliver(&url, &body, &settings(3), "s3cret"))
+                .await
+                .unwrap();
+        assert_eq!(result, Ok(()));
+
+        let first = rx.recv().unwrap();
+        let second = rx.recv().unwrap();
+        assert_eq!(second.path, "/hook");
+        assert_eq!(first.body, second.body);
+        let timestamp = second.header("X-Harold-Timestamp").unwrap();
+        let signature = second.header("X-Harold-Signature").unwrap();
+        assert_eq!(
+            signature,
+            format!("sha256={}", sign("s3cret", timestamp, &second.body))
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn deliver_gives_up_on_client_errors() {
+        crate::settings::init_settings_for_test();
+        let (base, rx) = stand_in::serve(vec![(400, ""), (200, "")]);
+
+        let result =
+            tokio::task::spawn_blocking(move || deliver(&base, "{}", &settings(3), "s3cret"))
+                .await
+                .unwrap();
+        assert_eq!(result, Err("HTTP 400".into()));
+        assert!(rx.recv().is_ok());
+        assert!(
+            rx.recv_timeout(Duration::from_millis(200)).is_err(),
+            "no retry after 400"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::proc::{Tool, run_blocking};
+use crate::settings::{PresenceBackend, get_settings};
+use crate::state;
+use crate::store::Presence;
+
+// ---------------------------------------------------------------------------
+// PresenceDetector — is the user at the desk or away?
+// ---------------------------------------------------------------------------
+
+pub trait PresenceDetector {
+    fn name(&self) -> &'static str;
+
+    /// `Some` when this detector has a definite answer. Automatic detectors only
+    /// answer `Away` (locked, idle); the manual override answers either way.
+    fn detect(&self) -> Option<Presence>;
+}
+
+fn detector(kind: PresenceBackend) -> Box<dyn PresenceDetector> {
+    match kind {
+        PresenceBackend::Manual => Box::new(Manual),
+        PresenceBackend::Macos => Box::new(MacosScreenLock),
+        PresenceBackend::Logind => Box::new(LogindLockedHint),
+        PresenceBackend::Idle => Box::new(IdleTime),
+    }
+}
+
+/// Consult `presence.detectors` in order; the first definite answer wins.
+/// With no answer the user is assumed to be at the desk.
+pub fn detect() -> Presence {
+    let detectors: Vec<Box<dyn PresenceDetector>> = get_settings()
+        .presence
+        .detectors
+        .iter()
+        .copied()
+        .map(detector)
+        .collect();
+    first_answer(detectors.iter().map(|d| d.as_ref()))
+}
+
+fn first_answer<'a>(detectors: impl IntoIterator<Item = &'a dyn PresenceDetector>) -> Presence {
+    for d in detectors {
+        if let Some(presence) = d.detect() {
+            debug!(detector = d.name(), ?presence, "presence detected");
+            return presence;
+        }
+    }
+    Presence::AtDesk
+}
+
+/// Each configured detector's own reading, for `--diagnostics`.
+pub fn readings() -> Vec<(&'static str, Option<Presence>)> {
+    get_settings()
+        .presence
+        .detectors
+        .iter()
+        .map(|&kind| {
+            let d = detector(kind);
+            (d.name(), d.detect())
+        })
+        .collect()
+}
+
+fn away_if(cond: bool) -> Option<Presence> {
+    cond.then_some(Presence::Away)
+}
+
+// ---------------------------------------------------------------------------
+// Backends
+// ---------------------------------------------------------------------------
+
+/// Override set with the `SetPresence` RPC or `harold --presence`.
+struct Manual;
+
+impl PresenceDetector for Manual {
+    fn name(&self) -> &'static str {
+        "manual"
+    }
+
+    fn detect(&self) -> Option<Presence> {
+        state::manual_presence()
+    }
+}
+
+/// macOS console lock (`IOConsoleLocked`).
+struct MacosScreenLock;
+
+impl PresenceDetector for MacosScreenLock {
+    fn name(&self) -> &'static str {
+        "macos"
+    }
+
+    fn detect(&self) -> Option<Presence> {
+        let mut cmd = Command::new("bash");
+        cmd.args([
+            "-c",
+            "ioreg -n Root -d1 -a | plutil -extract IOConsoleLocked raw -",
+        ]);
+        let out = run_blocking(cmd, Tool::ScreenLock).ok()?;
+        away_if(String::from_utf8_lossy(&out.stdout).trim() == "true")
+    }
+}
+
+/// systemd-logind `LockedHint`, set by screen lockers on most Linux desktops.
+struct LogindLockedHint;
+
+impl PresenceDetector for LogindLockedHint {
+    fn name(&self) -> &'static str {
+        "logind"
+    }
+
+    fn detect(&self) -> Option<Presence> {
+        let session = &get_settings().presence.logind_session;
+        let mut cmd = Command::new("loginctl");
+        cmd.args(["show-session", session, "-p", "LockedHint", "--value"]);
+        let out = run_blocking(cmd, Tool::ScreenLock).ok()?;
+        away_if(out.status.success() && String::from_utf8_lossy(&out.stdout).trim() == "yes")
+    }
+}
+
+/// Input idle time: GNOME's Mutter idle monitor on Wayland, `xprintidle` on X11.
+struct IdleTime;
+
+impl IdleTime {
+    fn idle_ms() -> Option<u64> {
+        let cmd = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            let mut cmd = Command::new("gdbus");
+            cmd.args([
+                "call",
+                "--session",
+                "--dest",
+                "org.gnome.Mutter.IdleMonitor",
+                "--object-path",
+                "/org/gnome/Mutter/IdleMonitor/Core",
+                "--method",
+                "org.gnome.Mutter.IdleMonitor.GetIdletime",
+            ]);
+            cmd
+        } else if std::env::var_os("DISPLAY").is_some() {
+            Command::new("xprintidle")
+        } else {
+            return None;
+        };
+        let out = run_blocking(cmd, Tool::ScreenLock).ok()?;
+        if !out.status.success() {
+            return None;
+        }
+        parse_idle_ms(&String::from_utf8_lossy(&out.stdout))
+    }
+}
+
+impl PresenceDetector for IdleTime {
+    fn name(&self) -> &'static str {
+        "idle"
+    }
+
+    fn detect(&self) -> Option<Presence> {
+        let idle_ms = Self::idle_ms()?;
+        away_if(idle_ms >= get_settings().presence.idle_away_secs.saturating_mul(1000))
+    }
+}
+
+/// Milliseconds from `xprintidle` (`12345`) or `gdbus` (`(uint64 12345,)`).
+fn parse_idle_ms(output: &str) -> Option<u64> {
+    output
+        .trim()
+        .trim_start_matches('(')
+        .trim_start_matches("uint64")
+        .trim_end_matches(')')
+        .trim_end_matches(',')
+        .trim()
+        .parse()
+        .ok()
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(Option<Presence>);
+
+    impl PresenceDetector for Fixed {
+        fn name(&self) -> &'static str {
+            "fixed"
+        }
+
+        fn detect(&self) -> Option<Presence> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn first_definite_answer_wins() {
+        let unknown = Fixed(None);
+        let here = Fixed(Some(Presence::AtDesk));
+        let away = Fixed(Some(Presence::Away));
+
+        let chain: [&dyn PresenceDetector; 2] = [&unknown, &away];
+        assert_eq!(first_answer(chain), Presence::Away);
+        // A manual "at desk" ahead of an idle detector masks it.
+        let chain: [&dyn PresenceDetector; 2] = [&here, &away];
+        assert_eq!(first_answer(chain), Presence::AtDesk);
+        let chain: [&dyn PresenceDetector; 1] = [&unknown];
+        assert_eq!(first_answer(chain), Presence::AtDesk);
+    }
+
+    #[test]
+    fn parse_idle_ms_reads_xprintidle_and_gdbus() {
+        assert_eq!(parse_idle_ms("4521\n"), Some(4521));
+        assert_eq!(parse_idle_ms("(uint64 98000,)\n"), Some(98000));
+        assert_eq!(parse_idle_ms("Error: no such interface"), None);
+    }
+}
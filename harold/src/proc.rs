@@ -0,0 +1,173 @@
+use std::process::{Output, Stdio};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::watch;
+use tracing::warn;
+
+use crate::settings::get_settings;
+
+// ---------------------------------------------------------------------------
+// Subprocess runner — every external tool runs with a deadline and is killed
+// on timeout or shutdown, so a hung helper can never wedge a worker.
+// ---------------------------------------------------------------------------
+
+static SHUTDOWN: OnceLock<watch::Receiver<()>> = OnceLock::new();
+
+/// Register the daemon's shutdown channel; running subprocesses are killed when it closes.
+pub fn init_shutdown(shutdown: watch::Receiver<()>) {
+    let _ = SHUTDOWN.set(shutdown);
+}
+
+/// External tools, each with its own configurable timeout (`[timeouts]`).
+#[derive(Debug, Clone, Copy)]
+pub enum Tool {
+    AiCli,
+    LocalModel,
+    Tmux,
+    Osascript,
+    Tts,
+    ScreenLock,
+    Http,
+    /// Telegram `getUpdates` or Matrix `/sync` long poll: the longer of the
+    /// two poll timeouts plus `http_secs`.
+    LongPoll,
+}
+
+impl Tool {
+    fn timeout(self) -> Duration {
+        let t = &get_settings().timeouts;
+        Duration::from_secs(match self {
+            Tool::AiCli => t.ai_cli_secs,
+            Tool::LocalModel => t.local_model_secs,
+            Tool::Tmux => t.tmux_secs,
+            Tool::Osascript => t.osascript_secs,
+            Tool::Tts => t.tts_secs,
+            Tool::ScreenLock => t.screen_lock_secs,
+            Tool::Http => t.http_secs,
+            Tool::LongPoll => {
+                let cfg = get_settings();
+                let poll = cfg
+                    .telegram
+                    .poll_timeout_secs
+                    .max(cfg.matrix.poll_timeout_secs);
+                poll.saturating_add(t.http_secs)
+            }
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProcError {
+    #[error("failed to spawn {program}: {source}")]
+    Spawn {
+        program: String,
+        source: std::io::Error,
+    },
+    #[error("{program} timed out after {timeout:?}")]
+    Timeout { program: String, timeout: Duration },
+    #[error("{program} cancelled by shutdown")]
+    Cancelled { program: String },
+    #[error("cannot run {program}: no tokio runtime on this thread")]
+    NoRuntime { program: String },
+}
+
+/// Run `cmd` to completion and capture its output. The child is killed if it
+/// outlives the tool's timeout or Harold shuts down. A non-zero exit is not an
+/// error — callers inspect `status` as before.
+pub async fn run(cmd: Command, tool: Tool) -> Result<Output, ProcError> {
+    run_with_input(cmd, tool, None).await
+}
+
+/// [`run`], writing `input` to the child's stdin first (e.g. a mail for `curl -T -`).
+pub async fn run_with_input(
+    mut cmd: Command,
+    tool: Tool,
+    input: Option<Vec<u8>>,
+) -> Result<Output, ProcError> {
+    let program = cmd.as_std().get_program().to_string_lossy().into_owned();
+    let timeout = tool.timeout();
+    // Dropping the output future (timeout/shutdown) drops the child, which kills it.
+    cmd.kill_on_drop(true);
+
+    let result = tokio::select! {
+        res = tokio::time::timeout(timeout, output(cmd, input)) => match res {
+            Ok(Ok(out)) => Ok(out),
+            Ok(Err(source)) => Err(ProcError::Spawn { program, source }),
+            Err(_) => Err(ProcError::Timeout { program, timeout }),
+        },
+        () = shutdown() => Err(ProcError::Cancelled { program }),
+    };
+    if let Err(e) = &result {
+        warn!(error = %e, ?tool, "subprocess did not complete");
+    }
+    result
+}
+
+/// Resolves when Harold starts shutting down; never without a shutdown channel.
+async fn shutdown() {
+    match SHUTDOWN.get().cloned().as_mut() {
+        Some(rx) => {
+            let _ = rx.changed().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+async fn output(mut cmd: Command, input: Option<Vec<u8>>) -> std::io::Result<Output> {
+    let Some(input) = input else {
+        return cmd.output().await;
+    };
+    cmd.stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = cmd.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A child that exits without reading all of it reports that in its status.
+        let _ = stdin.write_all(&input).await;
+    }
+    child.wait_with_output().await
+}
+
+/// The runtime a blocking caller waits on. Outside any runtime this is an
+/// error rather than the panic `Handle::current` would raise.
+fn blocking_handle(cmd: &Command) -> Result<tokio::runtime::Handle, ProcError> {
+    tokio::runtime::Handle::try_current().map_err(|_| ProcError::NoRuntime {
+        program: cmd.as_std().get_program().to_string_lossy().into_owned(),
+    })
+}
+
+/// [`run`] for synchronous code. Must be called inside `spawn_blocking`: on an
+/// async worker thread `block_on` panics, and tokio cannot tell the two apart
+/// before it does. Returns [`ProcError::NoRuntime`] outside a runtime.
+pub fn run_blocking(cmd: Command, tool: Tool) -> Result<Output, ProcError> {
+    blocking_handle(&cmd)?.block_on(run(cmd, tool))
+}
+
+/// Sleep for `wait` in synchronous code, waking early on shutdown. Returns
+/// whether the full wait elapsed. Same rules as [`run_blocking`]; outside a
+/// runtime there is no shutdown to wait on, so it just sleeps.
+pub fn sleep_blocking(wait: Duration) -> bool {
+    let Ok(handle) = tokio::runtime::Handle::try_current() else {
+        std::thread::sleep(wait);
+        return true;
+    };
+    handle.block_on(async {
+        tokio::select! {
+            () = tokio::time::sleep(wait) => true,
+            () = shutdown() => false,
+        }
+    })
+}
+
+/// [`run_with_input`] for synchronous code, under the same rules as
+/// [`run_blocking`].
+pub fn run_blocking_with_input(
+    cmd: Command,
+    tool: Tool,
+    input: Vec<u8>,
+) -> Result<Output, ProcError> {
+    blocking_handle(&cmd)?.block_on(run_with_input(cmd, tool, Some(input)))
+}
@@ -1,66 +1,526 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use events::{EventEnvelope, EventStore, Projector, Result};
-use tokio::sync::watch;
+use time::OffsetDateTime;
+use tokio::sync::{Mutex, watch};
+use tokio::task::JoinSet;
 use tracing::{Instrument, info, info_span, warn};
 
-use crate::inbound::route_reply;
-use crate::outbound::notify;
-use crate::store::{ReplyReceived, TurnCompleted};
+use crate::chat_db::sent;
+use crate::inbound::commands::ControlStep;
+use crate::inbound::{ReplyOutcome, handle_reply, scan_live_panes};
+use crate::outbound::{NotifyOutcome, notify, notify_digest};
+use crate::settings::get_settings;
+use crate::state;
+use crate::store::{
+    AwayNotificationSent, ControlKeyCancelled, ControlKeyRequested, ControlKeySent,
+    NotificationFailed, NotificationSent, NotificationSkipped, OfflineDigestSent,
+    PaneHandleAssigned, ReplyCommandHandled, ReplyLaneStarted, ReplyReceived, ReplyRouted,
+    ReplyRoutingFailed, SkipReason, TurnCompleted, append_away_notification_sent,
+    append_control_key_cancelled, append_control_key_requested, append_control_key_sent,
+    append_imessage_sent, append_notification_failed, append_notification_sent,
+    append_notification_skipped, append_offline_digest_sent, append_pane_handle_assigned,
+    append_reply_command_handled, append_reply_lane_started, append_reply_routed,
+    append_reply_routing_failed, next_version,
+};
 
-pub async fn run_projector(store: Arc<EventStore>, mut shutdown: watch::Receiver<()>) {
-    let projector = Projector::new(store, "harold.notifier".into());
-    info!("projector starting");
+// ---------------------------------------------------------------------------
+// Staleness — events that arrived while Harold was not running
+// ---------------------------------------------------------------------------
+
+fn older_than(at: OffsetDateTime, max_age_secs: u64) -> bool {
+    let age = OffsetDateTime::now_utc() - at;
+    age > time::Duration::seconds(i64::try_from(max_age_secs).unwrap_or(i64::MAX))
+}
+
+async fn skip_stale_turn(store: &EventStore, turn: &TurnCompleted, trace_id: String) {
+    info!(pane_label = %turn.pane_label, "projector: TurnCompleted is stale, skipping");
+    let skipped = NotificationSkipped {
+        trace_id,
+        pane_id: turn.pane_id.clone(),
+        pane_label: turn.pane_label.clone(),
+        reason: SkipReason::Stale,
+    };
+    if let Err(e) = append_notification_skipped(store, &skipped).await {
+        warn!(error = %e, "projector: failed to append notification outcome");
+    }
+}
+
+/// Record a reply that waited too long to be relayed.
+async fn skip_stale_reply(store: &EventStore, trace_id: String, max_age_secs: u64) {
+    info!("projector: ReplyReceived is stale, skipping");
+    let failed = ReplyRoutingFailed {
+        trace_id,
+        reason: format!("stale: received more than {max_age_secs}s ago"),
+        available_panes: vec![],
+    };
+    if let Err(e) = append_reply_routing_failed(store, &failed).await {
+        warn!(error = %e, "projector: failed to append routing outcome");
+    }
+}
+
+/// Record the iMessages a blocking send claimed. Each stays claimed in memory
+/// until its event is appended.
+async fn record_sent_imessages(store: &EventStore) {
+    for sent in sent::unrecorded() {
+        match append_imessage_sent(store, &sent).await {
+            Ok(()) => sent::recorded(sent.rowid),
+            Err(e) => warn!(error = %e, "projector: failed to append ImessageSent"),
+        }
+    }
+}
+
+async fn send_offline_digest(store: &EventStore, pane_labels: Vec<String>) {
+    info!(
+        count = pane_labels.len(),
+        "projector: sending offline digest"
+    );
+    let inner_span = tracing::Span::current();
+    let result = tokio::task::spawn_blocking(move || {
+        let _g = inner_span.entered();
+        let outcome = notify_digest(&pane_labels);
+        (pane_labels, outcome)
+    })
+    .await;
+    record_sent_imessages(store).await;
+    match result {
+        Ok((pane_labels, NotifyOutcome::Sent { channel, .. })) => {
+            let sent = OfflineDigestSent {
+                channel: channel.into(),
+                pane_labels,
+            };
+            if let Err(e) = append_offline_digest_sent(store, &sent).await {
+                warn!(error = %e, "projector: failed to append OfflineDigestSent");
+            }
+        }
+        Ok((_, outcome)) => info!(?outcome, "projector: offline digest not sent"),
+        Err(e) => warn!(error = %e, "projector: digest task panicked"),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Event handlers
+// ---------------------------------------------------------------------------
+
+async fn handle_turn_completed(store: &EventStore, turn: TurnCompleted, trace_id: String) {
+    info!(
+        pane_label = %turn.pane_label,
+        main_context = %turn.main_context,
+        "projector: TurnCompleted"
+    );
+    assign_pane_handle(store, &turn).await;
+    let inner_span = tracing::Span::current();
+    let tid = trace_id.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let _g = inner_span.entered();
+        let outcomes = notify(&turn, &tid);
+        (turn, outcomes)
+    })
+    .await;
+    record_sent_imessages(store).await;
+    let (turn, outcomes) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            warn!(error = %e, "projector: notify task panicked");
+            return;
+        }
+    };
+
+    for outcome in outcomes {
+        record_outcome(store, &turn, &trace_id, outcome).await;
+    }
+}
+
+/// Held while a handle is picked and recorded, so panes notified at the same
+/// time cannot pick the same free handle.
+static ASSIGNING_HANDLE: Mutex<()> = Mutex::const_new(());
+
+/// Give the turn's pane a `#handle` before it is notified, unless it already
+/// has one under its current label.
+async fn assign_pane_handle(store: &EventStore, turn: &TurnCompleted) {
+    let _assigning = ASSIGNING_HANDLE.lock().await;
+    let handle = match state::pane_handle(&turn.pane_id) {
+        Some((_, label)) if label == turn.pane_label => return,
+        Some((handle, _)) => handle,
+        None => {
+            let live = match tokio::task::spawn_blocking(scan_live_panes).await {
+                Ok(live) => live,
+                Err(e) => {
+                    warn!(error = %e, "projector: pane scan task panicked");
+                    return;
+                }
+            };
+            // A failed or partial scan would make held handles look free and
+            // take them from live panes. The turn's own pane must be in it.
+            if !live.iter().any(|p| p.pane_id() == turn.pane_id) {
+                warn!(
+                    panes = live.len(),
+                    "projector: pane scan missed this pane; not assigning a handle"
+                );
+                return;
+            }
+            let live_ids: Vec<&str> = live.iter().map(|p| p.pane_id()).collect();
+            state::free_handle(&live_ids)
+        }
+    };
+    let assigned = PaneHandleAssigned {
+        handle,
+        pane_id: turn.pane_id.clone(),
+        pane_label: turn.pane_label.clone(),
+    };
+    if let Err(e) = append_pane_handle_assigned(store, &assigned).await {
+        warn!(error = %e, "projector: failed to append PaneHandleAssigned");
+    }
+}
+
+/// An event recorded for a notification outcome.
+#[derive(Debug)]
+enum OutcomeEvent {
+    Sent(NotificationSent),
+    AwaySent(AwayNotificationSent),
+    Skipped(NotificationSkipped),
+    Failed(NotificationFailed),
+}
+
+/// The events for one channel's notification of `turn`, in append order. An
+/// away notification also records its source agent and reply tokens.
+fn outcome_events(
+    turn: &TurnCompleted,
+    trace_id: &str,
+    outcome: NotifyOutcome,
+) -> Vec<OutcomeEvent> {
+    let trace_id = trace_id.to_string();
+    let pane_id = turn.pane_id.clone();
+    let pane_label = turn.pane_label.clone();
+    match outcome {
+        NotifyOutcome::Sent {
+            channel,
+            source_agent,
+            reply_tokens,
+        } => {
+            let mut events = vec![OutcomeEvent::Sent(NotificationSent {
+                trace_id,
+                pane_id,
+                pane_label,
+                channel: channel.into(),
+            })];
+            if let Some(agent) = source_agent {
+                let mut tokens = reply_tokens.into_iter();
+                events.push(OutcomeEvent::AwaySent(AwayNotificationSent {
+                    pane_id: agent.pane_id().to_string(),
+                    pane_label: agent.label().to_string(),
+                    reply_token: tokens.next(),
+                    extra_reply_tokens: tokens.collect(),
+                }));
+            }
+            events
+        }
+        NotifyOutcome::Skipped(reason) => vec![OutcomeEvent::Skipped(NotificationSkipped {
+            trace_id,
+            pane_id,
+            pane_label,
+            reason,
+        })],
+        NotifyOutcome::Failed { channel, reason } => {
+            vec![OutcomeEvent::Failed(NotificationFailed {
+                trace_id,
+                pane_id,
+                pane_label,
+                channel: channel.into(),
+                reason,
+            })]
+        }
+    }
+}
+
+/// Append the outcome events for one channel's notification of `turn`.
+async fn record_outcome(
+    store: &EventStore,
+    turn: &TurnCompleted,
+    trace_id: &str,
+    outcome: NotifyOutcome,
+) {
+    for event in outcome_events(turn, trace_id, outcome) {
+        let appended = match &event {
+            OutcomeEvent::Sent(e) => append_notification_sent(store, e).await,
+            OutcomeEvent::AwaySent(e) => append_away_notification_sent(store, e).await,
+            OutcomeEvent::Skipped(e) => append_notification_skipped(store, e).await,
+            OutcomeEvent::Failed(e) => append_notification_failed(store, e).await,
+        };
+        if let Err(e) = appended {
+            warn!(error = %e, ?event, "projector: failed to append notification outcome");
+        }
+    }
+}
+
+async fn record_control_step(store: &EventStore, trace_id: &str, step: ControlStep) {
+    let trace_id = trace_id.to_string();
+    let appended = match step {
+        ControlStep::Requested(t) => {
+            let requested = ControlKeyRequested {
+                trace_id,
+                pane_id: t.pane_id,
+                label: t.label,
+                key: t.key,
+            };
+            append_control_key_requested(store, &requested).await
+        }
+        ControlStep::Sent(t) => {
+            let sent = ControlKeySent {
+                trace_id,
+                pane_id: t.pane_id,
+                label: t.label,
+                key: t.key,
+            };
+            append_control_key_sent(store, &sent).await
+        }
+        ControlStep::Cancelled => {
+            append_control_key_cancelled(store, &ControlKeyCancelled { trace_id }).await
+        }
+    };
+    if let Err(e) = appended {
+        warn!(error = %e, "projector: failed to append control key event");
+    }
+}
+
+async fn handle_reply_received(store: &EventStore, reply: ReplyReceived, trace_id: String) {
+    info!("projector: ReplyReceived");
+    let inner_span = tracing::Span::current();
+    let result = tokio::task::spawn_blocking(move || {
+        let _g = inner_span.entered();
+        handle_reply(&reply)
+    })
+    .await;
+    record_sent_imessages(store).await;
+    let outcome = match result {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            warn!(error = %e, "projector: handle_reply task panicked");
+            return;
+        }
+    };
+
+    let appended = match outcome {
+        ReplyOutcome::Command {
+            name,
+            error,
+            control,
+        } => {
+            if let Some(step) = control {
+                record_control_step(store, &trace_id, step).await;
+            }
+            let handled = ReplyCommandHandled {
+                trace_id,
+                command: name,
+                error,
+            };
+            append_reply_command_handled(store, &handled).await
+        }
+        ReplyOutcome::Routed(Ok(routed)) => {
+            let mut appended = Ok(());
+            for (agent, method) in routed {
+                let routed = ReplyRouted {
+                    trace_id: trace_id.clone(),
+                    pane_id: agent.pane_id().to_string(),
+                    label: agent.label().to_string(),
+                    method,
+                };
+                if let Err(e) = append_reply_routed(store, &routed).await {
+                    appended = Err(e);
+                }
+            }
+            appended
+        }
+        ReplyOutcome::Routed(Err(e)) => {
+            let failed = ReplyRoutingFailed {
+                trace_id,
+                available_panes: e.available().to_vec(),
+                reason: e.to_string(),
+            };
+            append_reply_routing_failed(store, &failed).await
+        }
+    };
+    if let Err(e) = appended {
+        warn!(error = %e, "projector: failed to append routing outcome");
+    }
+}
+
+/// Events recorded for history and state only — no side effects to drive.
+const RECORD_ONLY: &[&str] = &[
+    "AgentMessageSent",
+    "AwayNotificationSent",
+    "ChatDbCursorSaved",
+    "ControlKeyCancelled",
+    "ControlKeyRequested",
+    "ControlKeySent",
+    "EmailCursorSaved",
+    "ImessageSent",
+    "ManualPresenceSet",
+    "MatrixCursorSaved",
+    "NotificationSent",
+    "NotificationSkipped",
+    "NotificationFailed",
+    "OfflineDigestSent",
+    "PaneHandleAssigned",
+    "ReplyCommandHandled",
+    "ReplyLaneStarted",
+    "ReplyRouted",
+    "ReplyRoutingFailed",
+    "TelegramCursorSaved",
+];
+
+/// Notify for one turn. Returns its pane label if it was stale, for the offline digest.
+async fn handle_turn(
+    store: &EventStore,
+    event_id: String,
+    turn: TurnCompleted,
+    at: OffsetDateTime,
+) -> Option<String> {
+    if older_than(at, get_settings().notify.max_turn_age_secs) {
+        skip_stale_turn(store, &turn, event_id).await;
+        return Some(turn.pane_label);
+    }
+    handle_turn_completed(store, turn, event_id).await;
+    None
+}
+
+type PaneTurns = Vec<(String, TurnCompleted, OffsetDateTime)>;
+
+/// Pick the `TurnCompleted` events out of a batch, keeping arrival order per pane.
+fn group_turns_by_pane(events: &[EventEnvelope]) -> HashMap<String, PaneTurns> {
+    let mut by_pane: HashMap<String, PaneTurns> = HashMap::new();
+    for e in events {
+        match e.r#type.as_str() {
+            "TurnCompleted" => match serde_json::from_value::<TurnCompleted>(e.payload.clone()) {
+                Ok(turn) => by_pane.entry(turn.pane_id.clone()).or_default().push((
+                    e.id.to_string(),
+                    turn,
+                    e.timestamp,
+                )),
+                Err(err) => warn!(
+                    error = %err,
+                    trace_id = %e.id,
+                    "projector: failed to deserialise TurnCompleted"
+                ),
+            },
+            // Handled by the reply lane.
+            "ReplyReceived" => {}
+            t if RECORD_ONLY.contains(&t) => {}
+            other => warn!(event_type = %other, "projector: unknown event type"),
+        }
+    }
+    by_pane
+}
+
+// ---------------------------------------------------------------------------
+// Lanes — notification and reply routing run independently so a slow summary
+// never delays a reply. Each lane is its own projector with its own checkpoint.
+// ---------------------------------------------------------------------------
+
+/// Notification lane. Turns in a batch are grouped by pane: each pane's turns are
+/// handled in order, different panes concurrently. The batch completes (and is
+/// checkpointed) once every pane is done.
+async fn run_notification_lane(store: Arc<EventStore>, mut shutdown: watch::Receiver<()>) {
+    let projector = Projector::new(Arc::clone(&store), "harold.notifier".into());
+    info!("notification lane starting");
+
+    let result: Result<()> = tokio::select! {
+        res = projector.run(|events: &[EventEnvelope]| {
+            // Clone all needed data before the async block — no references may escape.
+            let by_pane = group_turns_by_pane(events);
+
+            let store = Arc::clone(&store);
+            async move {
+                let mut panes = JoinSet::new();
+                for (_, turns) in by_pane {
+                    let store = Arc::clone(&store);
+                    panes.spawn(async move {
+                        let mut stale = Vec::new();
+                        for (event_id, turn, at) in turns {
+                            let span = info_span!("event", trace_id = %event_id);
+                            let stale_label = handle_turn(&store, event_id, turn, at)
+                                .instrument(span)
+                                .await;
+                            stale.extend(stale_label);
+                        }
+                        stale
+                    });
+                }
+
+                let mut stale = Vec::new();
+                while let Some(res) = panes.join_next().await {
+                    match res {
+                        Ok(labels) => stale.extend(labels),
+                        Err(e) => warn!(error = %e, "projector: pane task panicked"),
+                    }
+                }
+                // Catch-up after downtime arrives in large batches; one digest per batch.
+                if !stale.is_empty() && get_settings().notify.stale_digest {
+                    send_offline_digest(&store, stale)
+                        .instrument(info_span!("offline_digest"))
+                        .await;
+                }
+                Ok(())
+            }
+        }) => res,
+        _ = shutdown.changed() => {
+            info!("notification lane shutting down");
+            Ok(())
+        }
+    };
+
+    if let Err(e) = result {
+        warn!(error = %e, "notification lane exited with error");
+    }
+}
+
+/// The first version the reply lane routes. On its first start the lane
+/// begins at the stream head: earlier replies were routed by the notification
+/// lane before the split, and must not reach an agent twice.
+async fn reply_lane_from(store: &EventStore) -> u64 {
+    if let Some(from_version) = state::reply_lane_from() {
+        return from_version;
+    }
+    let started = ReplyLaneStarted {
+        from_version: next_version(),
+    };
+    if let Err(e) = append_reply_lane_started(store, &started).await {
+        warn!(error = %e, "projector: failed to append ReplyLaneStarted");
+    }
+    started.from_version
+}
+
+/// Reply lane. Replies are routed strictly in arrival order.
+async fn run_reply_lane(store: Arc<EventStore>, mut shutdown: watch::Receiver<()>) {
+    let from_version = reply_lane_from(&store).await;
+    let projector = Projector::new(Arc::clone(&store), "harold.router".into());
+    info!(from_version, "reply lane starting");
 
     let result: Result<()> = tokio::select! {
         res = projector.run(|events: &[EventEnvelope]| {
             // Clone all needed data before the async block — no references may escape.
-            let batch: Vec<(String, String, serde_json::Value)> = events
+            let batch: Vec<(String, serde_json::Value, OffsetDateTime)> = events
                 .iter()
-                .map(|e| (e.id.to_string(), e.r#type.clone(), e.payload.clone()))
+                .filter(|e| e.r#type == "ReplyReceived" && e.version >= from_version)
+                .map(|e| (e.id.to_string(), e.payload.clone(), e.timestamp))
                 .collect();
 
+            let store = Arc::clone(&store);
             async move {
-                for (event_id, event_type, payload) in batch {
+                for (event_id, payload, at) in batch {
                     let span = info_span!("event", trace_id = %event_id);
-
                     async {
-                        match event_type.as_str() {
-                            "TurnCompleted" => {
-                                match serde_json::from_value::<TurnCompleted>(payload) {
-                                    Ok(turn) => {
-                                        info!(
-                                            pane_label = %turn.pane_label,
-                                            main_context = %turn.main_context,
-                                            "projector: TurnCompleted"
-                                        );
-                                        let inner_span = tracing::Span::current();
-                                        let tid = event_id.clone();
-                                        tokio::task::spawn_blocking(move || {
-                                            let _g = inner_span.entered();
-                                            notify(&turn, &tid);
-                                        }).await.ok();
-                                    }
-                                    Err(e) => warn!(error = %e, "projector: failed to deserialise TurnCompleted"),
-                                }
-                            }
-                            "ReplyReceived" => {
-                                match serde_json::from_value::<ReplyReceived>(payload) {
-                                    Ok(reply) => {
-                                        info!("projector: ReplyReceived");
-                                        let inner_span = tracing::Span::current();
-                                        tokio::task::spawn_blocking(move || {
-                                            let _g = inner_span.entered();
-                                            route_reply(&reply.text);
-                                        })
-                                        .await
-                                        .ok();
-                                    }
-                                    Err(e) => warn!(error = %e, "projector: failed to deserialise ReplyReceived"),
-                                }
-                            }
-                            other => {
-                                warn!(event_type = %other, "projector: unknown event type");
+                        // Never replay old replies into agents, e.g. after a long stop.
+                        let max_age = get_settings().inbound.max_reply_age_secs;
+                        if older_than(at, max_age) {
+                            skip_stale_reply(&store, event_id, max_age).await;
+                            return;
+                        }
+                        match serde_json::from_value::<ReplyReceived>(payload) {
+                            Ok(reply) => handle_reply_received(&store, reply, event_id).await,
+                            Err(e) => {
+                                warn!(error = %e, "projector: failed to deserialise ReplyReceived")
                             }
                         }
                     }
@@ -71,12 +531,90 @@ pub async fn run_projector(store: Arc<EventStore>, mut shutdown: watch::Receiver
             }
         }) => res,
         _ = shutdown.changed() => {
-            info!("projector shutting down");
+            info!("reply lane shutting down");
             Ok(())
         }
     };
 
     if let Err(e) = result {
-        warn!(error = %e, "projector exited with error");
+        warn!(error = %e, "reply lane exited with error");
+    }
+}
+
+pub async fn run_projector(store: Arc<EventStore>, shutdown: watch::Receiver<()>) {
+    tokio::join!(
+        run_notification_lane(Arc::clone(&store), shutdown.clone()),
+        run_reply_lane(store, shutdown),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inbound::AgentAddress;
+
+    fn turn() -> TurnCompleted {
+        TurnCompleted {
+            pane_id: "%3".into(),
+            pane_label: "harold:0.1".into(),
+            last_user_prompt: "fix it".into(),
+            assistant_message: "Fixed.".into(),
+            main_context: "main".into(),
+        }
+    }
+
+    #[test]
+    fn away_outcome_records_source_agent_and_reply_tokens() {
+        let outcome = NotifyOutcome::Sent {
+            channel: "matrix",
+            source_agent: Some(AgentAddress::TmuxPane {
+                pane_id: "%3".into(),
+                label: "harold:0.1".into(),
+            }),
+            reply_tokens: vec!["$main".into(), "$question".into()],
+        };
+        let events = outcome_events(&turn(), "t1", outcome);
+        let [OutcomeEvent::Sent(sent), OutcomeEvent::AwaySent(away)] = events.as_slice() else {
+            panic!("unexpected events: {events:?}");
+        };
+        assert_eq!(sent.trace_id, "t1");
+        assert_eq!(sent.channel, "matrix");
+        assert_eq!(away.pane_id, "%3");
+        assert_eq!(away.reply_token.as_deref(), Some("$main"));
+        assert_eq!(away.extra_reply_tokens, ["$question"]);
+    }
+
+    #[test]
+    fn desk_skip_and_failure_outcomes_record_one_event() {
+        let sent = NotifyOutcome::Sent {
+            channel: "tts",
+            source_agent: None,
+            reply_tokens: vec![],
+        };
+        assert!(matches!(
+            outcome_events(&turn(), "t1", sent).as_slice(),
+            [OutcomeEvent::Sent(_)]
+        ));
+
+        let skipped = NotifyOutcome::Skipped(SkipReason::Duplicate);
+        assert!(matches!(
+            outcome_events(&turn(), "t1", skipped).as_slice(),
+            [OutcomeEvent::Skipped(NotificationSkipped {
+                reason: SkipReason::Duplicate,
+                ..
+            })]
+        ));
+
+        let failed = NotifyOutcome::Failed {
+            channel: "imessage",
+            reason: "osascript exited with status 1".into(),
+        };
+        let events = outcome_events(&turn(), "t1", failed);
+        let [OutcomeEvent::Failed(failed)] = events.as_slice() else {
+            panic!("unexpected events: {events:?}");
+        };
+        assert_eq!(failed.pane_label, "harold:0.1");
+        assert_eq!(failed.channel, "imessage");
+        assert_eq!(failed.reason, "osascript exited with status 1");
     }
 }
@@ -43,6 +43,8 @@ pub struct ImessageSettings {
 #[derive(Debug, Deserialize)]
 pub struct ChatDbSettings {
     pub path: String,
+    /// Messages older than this are not replayed when catching up after downtime.
+    pub max_catch_up_secs: u64,
 }
 
 impl ChatDbSettings {
@@ -51,11 +53,44 @@ impl ChatDbSettings {
     }
 }
 
+/// A summarisation backend, named in the per-call-site chains under `[ai]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummarizerBackend {
+    /// The AI CLI at `ai.cli_path`.
+    Cli,
+    /// `mlx_lm.generate` with `ai.local_model` (Apple silicon).
+    Mlx,
+    /// An OpenAI-compatible endpoint configured in `[ai.http]` (e.g. Ollama).
+    Http,
+    /// Offline heuristic over the source text; always available.
+    Extractive,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HttpModelSettings {
+    /// Full chat completions URL, e.g. `http://localhost:11434/v1/chat/completions`.
+    pub url: String,
+    pub model: String,
+    pub api_key: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AiSettings {
     pub cli_path: Option<String>,
+    pub cli_model: String,
     pub local_model: Option<String>,
     pub local_model_dir: Option<String>,
+    pub http: Option<HttpModelSettings>,
+    /// Backends tried in order for the iMessage summary.
+    pub imessage_summary: Vec<SummarizerBackend>,
+    /// Backends tried in order for the desktop notification summary.
+    pub desktop_summary: Vec<SummarizerBackend>,
+    /// Backends tried in order for the spoken 3-8 word summary.
+    pub tts_summary: Vec<SummarizerBackend>,
+    /// Backends tried in order for the webhook payload summary, when the
+    /// primary channel has not already summarised the turn.
+    pub webhook_summary: Vec<SummarizerBackend>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -81,10 +116,179 @@ impl StoreSettings {
     }
 }
 
+/// How to notify while the user is at the desk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AtDeskChannel {
+    /// Speak a short summary with the TTS command.
+    Tts,
+    /// Silent freedesktop popup over D-Bus.
+    Desktop,
+}
+
+/// How to notify, and take replies, while the user is away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AwayChannel {
+    /// Messages.app via `osascript`; replies polled from `chat.db`.
+    Imessage,
+    /// Telegram Bot API `sendMessage`; replies long-polled with `getUpdates`.
+    Telegram,
+    /// SMTP with a reply token; replies polled from an IMAP folder.
+    Email,
+    /// Matrix room, one thread per notification; replies long-polled with `/sync`.
+    Matrix,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct NotifySettings {
+    pub at_desk_channel: AtDeskChannel,
+    pub away_channel: AwayChannel,
     pub skip_if_session_active: bool,
     pub skip_if_pane_active: bool,
+    /// Turns older than this when the projector reaches them are not notified.
+    pub max_turn_age_secs: u64,
+    /// Fold stale turns into one "while Harold was offline" notification.
+    pub stale_digest: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DesktopSettings {
+    pub app_name: String,
+    /// Passed to `Notify`; -1 lets the notification server decide.
+    pub expire_timeout_ms: i32,
+    /// How long to listen for "Jump to pane" / "Dismiss" after showing.
+    pub action_wait_secs: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TelegramSettings {
+    /// Bot API root; point at a local server in tests or behind a proxy.
+    pub api_base: String,
+    /// Token from @BotFather. Best set via `HAROLD__TELEGRAM__BOT_TOKEN`.
+    pub bot_token: Option<String>,
+    /// The only chat notifications go to and replies are accepted from.
+    pub chat_id: Option<i64>,
+    /// `getUpdates` long-poll timeout.
+    pub poll_timeout_secs: u64,
+    /// Replies older than this when first seen are not routed.
+    pub max_catch_up_secs: u64,
+}
+
+/// A place replies are read from (`inbound.sources`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InboundSourceKind {
+    /// chat.db, polled on each change.
+    Imessage,
+    Telegram,
+    Email,
+    Matrix,
+    /// Lines written to `inbound.socket_path`.
+    Socket,
+    /// Lines on standard input, for testing.
+    Stdin,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InboundSettings {
+    /// Sources run alongside the away channel's own.
+    pub sources: Vec<InboundSourceKind>,
+    pub socket_path: String,
+    /// Replies older than this when the reply lane reaches them are not routed.
+    pub max_reply_age_secs: u64,
+}
+
+impl InboundSettings {
+    pub fn resolved_socket_path(&self) -> String {
+        expand_tilde(&self.socket_path)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MatrixSettings {
+    /// Homeserver base URL, e.g. `https://matrix.example.org`.
+    pub homeserver: Option<String>,
+    /// Access token of Harold's account. Best set via `HAROLD__MATRIX__ACCESS_TOKEN`.
+    pub access_token: Option<String>,
+    /// Room notifications are posted to, e.g. `!abc123:example.org`.
+    pub room_id: Option<String>,
+    /// The only user whose messages in the room are taken as replies.
+    pub allowed_user: Option<String>,
+    /// `/sync` long-poll timeout.
+    pub poll_timeout_secs: u64,
+    /// Replies older than this when first seen are not routed.
+    pub max_catch_up_secs: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmailSettings {
+    /// `smtps://host:465`, or `smtp://host:587` with `require_tls`.
+    pub smtp_url: Option<String>,
+    /// Folder to read replies from, e.g. `imaps://host:993/INBOX`.
+    pub imap_url: Option<String>,
+    pub username: Option<String>,
+    /// Best set via `HAROLD__EMAIL__PASSWORD`.
+    pub password: Option<String>,
+    /// Sender address, e.g. `Harold <harold@example.com>`.
+    pub from: Option<String>,
+    /// The recipient; replies are only accepted from this address.
+    pub to: Option<String>,
+    /// Insist on TLS (STARTTLS for `smtp://` and `imap://` URLs).
+    pub require_tls: bool,
+    pub poll_secs: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookSettings {
+    /// Every URL receives each notification; empty disables the channel.
+    pub urls: Vec<String>,
+    /// HMAC-SHA256 key for `X-Harold-Signature`. Required when `urls` is set.
+    pub secret: Option<String>,
+    /// Attempts per URL on network errors, 5xx and 429.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled after each, up to 30 s.
+    pub initial_backoff_ms: u64,
+}
+
+/// A presence detector, named in `presence.detectors`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceBackend {
+    /// Override set with `SetPresence` / `harold --presence`.
+    Manual,
+    /// `IOConsoleLocked` via `ioreg`.
+    Macos,
+    /// systemd-logind `LockedHint` via `loginctl`.
+    Logind,
+    /// Input idle time via Mutter (Wayland) or `xprintidle` (X11).
+    Idle,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PresenceSettings {
+    /// Consulted in order; the first definite answer wins, otherwise "at desk".
+    pub detectors: Vec<PresenceBackend>,
+    /// Session passed to `loginctl show-session`.
+    pub logind_session: String,
+    /// Idle longer than this counts as away.
+    pub idle_away_secs: u64,
+}
+
+/// Per-tool subprocess deadlines. A tool that overruns is killed and its caller
+/// falls back (truncation, "Work complete", no routing match, ...).
+#[derive(Debug, Deserialize)]
+pub struct TimeoutSettings {
+    pub ai_cli_secs: u64,
+    pub local_model_secs: u64,
+    pub tmux_secs: u64,
+    /// Busy timeout for chat.db reads while Messages holds a write lock.
+    pub sqlite_secs: u64,
+    pub osascript_secs: u64,
+    pub tts_secs: u64,
+    pub screen_lock_secs: u64,
+    pub http_secs: u64,
+    pub dbus_secs: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -97,16 +301,73 @@ pub struct Settings {
     pub log: LogSettings,
     pub store: StoreSettings,
     pub notify: NotifySettings,
+    pub desktop: DesktopSettings,
+    pub telegram: TelegramSettings,
+    pub email: EmailSettings,
+    pub matrix: MatrixSettings,
+    pub inbound: InboundSettings,
+    pub webhook: WebhookSettings,
+    pub presence: PresenceSettings,
+    pub timeouts: TimeoutSettings,
 }
 
 impl Settings {
     pub fn validate(&self) -> Vec<String> {
         let mut errors = Vec::new();
-        if self.imessage.recipient.is_none() {
-            errors.push("imessage.recipient is required".into());
+        match self.notify.away_channel {
+            AwayChannel::Imessage => {
+                if self.imessage.recipient.is_none() {
+                    errors.push("imessage.recipient is required".into());
+                }
+                if self.imessage.handle_ids.is_empty() {
+                    errors.push("imessage.handle_ids requires at least one handle ID".into());
+                }
+            }
+            AwayChannel::Telegram => {
+                if self.telegram.bot_token.is_none() {
+                    errors.push(
+                        "telegram.bot_token is required when away_channel = \"telegram\"".into(),
+                    );
+                }
+                if self.telegram.chat_id.is_none() {
+                    errors.push(
+                        "telegram.chat_id is required when away_channel = \"telegram\"".into(),
+                    );
+                }
+            }
+            AwayChannel::Email => {
+                let e = &self.email;
+                for (key, value) in [
+                    ("smtp_url", &e.smtp_url),
+                    ("imap_url", &e.imap_url),
+                    ("from", &e.from),
+                    ("to", &e.to),
+                ] {
+                    if value.is_none() {
+                        errors.push(format!(
+                            "email.{key} is required when away_channel = \"email\""
+                        ));
+                    }
+                }
+            }
+            AwayChannel::Matrix => {
+                let m = &self.matrix;
+                for (key, value) in [
+                    ("homeserver", &m.homeserver),
+                    ("access_token", &m.access_token),
+                    ("room_id", &m.room_id),
+                    ("allowed_user", &m.allowed_user),
+                ] {
+                    if value.is_none() {
+                        errors.push(format!(
+                            "matrix.{key} is required when away_channel = \"matrix\""
+                        ));
+                    }
+                }
+            }
         }
-        if self.imessage.handle_ids.is_empty() {
-            errors.push("imessage.handle_ids requires at least one handle ID".into());
+        if !self.webhook.urls.is_empty() && self.webhook.secret.is_none() {
+            errors.push("webhook.secret is required when webhook.urls is set".into());
         }
         errors
     }
@@ -0,0 +1,363 @@
+use std::path::Path;
+use std::time::Duration;
+
+use events::EventStore;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use super::{Batch, InboundSource};
+use crate::chat_db::{ChatDbError, Message, Reader, sent, with_reader};
+use crate::settings::get_settings;
+use crate::state;
+use crate::store::{ChatDbCursorSaved, ReplyReceived, append_chat_db_cursor_saved};
+
+/// Seconds between the Unix epoch and the Apple (Core Data) epoch, 2001-01-01.
+const APPLE_EPOCH_OFFSET_SECS: i64 = 978_307_200;
+
+/// Poll this often even without filesystem events, in case the watcher misses some.
+const FALLBACK_POLL: Duration = Duration::from_secs(5);
+
+fn db_path() -> String {
+    get_settings().chat_db.resolved_path()
+}
+
+/// `message.date` value (nanoseconds since the Apple epoch) for a Unix timestamp.
+fn apple_date_nanos(unix_secs: i64) -> i64 {
+    (unix_secs - APPLE_EPOCH_OFFSET_SECS).saturating_mul(1_000_000_000)
+}
+
+/// Where polling starts. Resumes from the saved cursor so replies sent while Harold
+/// was down are not lost, but skips anything older than `max_age_secs` before
+/// `now`. Without a saved cursor (first run), starts at the newest message.
+fn initial_cursors(
+    reader: &Reader,
+    saved: Option<ChatDbCursorSaved>,
+    now: i64,
+    max_age_secs: i64,
+) -> Result<(i64, i64), ChatDbError> {
+    let Some(saved) = saved else {
+        let max = reader.max_rowid()?;
+        return Ok((max, max));
+    };
+    let floor = reader.last_rowid_before(apple_date_nanos(now.saturating_sub(max_age_secs)))?;
+    Ok((saved.inbound_rowid.max(floor), saved.self_rowid.max(floor)))
+}
+
+/// Inbound and self-sent rows after the two cursors. Holds the send lock, so
+/// a message Harold is sending is claimed before it can be read here.
+fn fetch(inbound_rowid: i64, self_rowid: i64) -> Result<(Vec<Message>, Vec<Message>), ChatDbError> {
+    let handle_ids = &get_settings().imessage.handle_ids;
+    let _sending = sent::sending();
+    with_reader(|reader| {
+        Ok((
+            reader.messages_after(inbound_rowid, handle_ids, false)?,
+            reader.messages_after(self_rowid, handle_ids, true)?,
+        ))
+    })
+}
+
+/// Replies from fetched rows, skipping blank rows and self-sent rows that are
+/// Harold's own messages. The batch ends after the last row read either way.
+fn batch(
+    inbound: Vec<Message>,
+    self_sent: Vec<Message>,
+    is_own: impl Fn(i64, &str) -> bool,
+) -> Batch<ChatDbCursor> {
+    let end = ChatDbCursor::Through {
+        inbound: inbound.last().map(|&(rowid, _)| rowid),
+        self_sent: self_sent.last().map(|&(rowid, _)| rowid),
+    };
+    let has_rows = !inbound.is_empty() || !self_sent.is_empty();
+    let replies = inbound
+        .into_iter()
+        .map(|(rowid, text)| (ChatDbCursor::Inbound(rowid), text))
+        .chain(
+            self_sent
+                .into_iter()
+                .filter(|(rowid, text)| !is_own(*rowid, text))
+                .map(|(rowid, text)| (ChatDbCursor::SelfSent(rowid), text)),
+        )
+        .filter_map(|(cursor, text)| {
+            let text = text.trim();
+            (!text.is_empty()).then(|| {
+                let reply = ReplyReceived {
+                    text: text.to_string(),
+                    reply_token: None,
+                };
+                (cursor, reply)
+            })
+        })
+        .collect();
+    Batch {
+        replies,
+        end: has_rows.then_some(end),
+    }
+}
+
+fn start_watcher(chat_db_path: &str) -> Option<(RecommendedWatcher, mpsc::UnboundedReceiver<()>)> {
+    let parent = Path::new(chat_db_path).parent()?;
+    let db_name = Path::new(chat_db_path).file_name()?.to_str()?.to_string();
+    let wal_name = format!("{db_name}-wal");
+
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    let watcher = notify::recommended_watcher(move |res: Result<notify::Event, notify::Error>| {
+        let event = match res {
+            Ok(e) => e,
+            Err(e) => {
+                warn!(error = %e, "fs watcher event error");
+                return;
+            }
+        };
+        if !matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
+            return;
+        }
+        let targets_chat_db = event.paths.iter().any(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n == db_name || n == wal_name)
+        });
+        if targets_chat_db {
+            let _ = tx.send(());
+        }
+    });
+
+    let mut watcher = match watcher {
+        Ok(w) => w,
+        Err(e) => {
+            warn!(error = %e, "failed to create fs watcher, falling back to poll-only");
+            return None;
+        }
+    };
+
+    if let Err(e) = watcher.watch(parent, RecursiveMode::NonRecursive) {
+        warn!(error = %e, "failed to watch chat.db directory, falling back to poll-only");
+        return None;
+    }
+
+    info!(path = %parent.display(), "fs watcher active on chat.db directory");
+    Some((watcher, rx))
+}
+
+// ---------------------------------------------------------------------------
+// ImessageSource — replies from chat.db
+// ---------------------------------------------------------------------------
+
+/// Position after one chat.db message; inbound and self-sent rows have their
+/// own cursor.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ChatDbCursor {
+    Inbound(i64),
+    SelfSent(i64),
+    /// The last rows read in each direction, replies or not.
+    Through {
+        inbound: Option<i64>,
+        self_sent: Option<i64>,
+    },
+}
+
+/// Inbound and self-sent iMessages from `imessage.handle_ids`, polled on each
+/// change to chat.db and every few seconds as a fallback.
+pub(crate) struct ImessageSource {
+    /// `(inbound, self)` rowids; resolved on the first poll that can read chat.db.
+    cursors: Option<(i64, i64)>,
+    /// Catch up on anything that arrived while Harold was down before waiting.
+    poll_now: bool,
+    // Keep _watcher alive (dropping it stops watching). In the fallback path,
+    // _keep_tx stays alive so fs_rx.recv() pends forever rather than returning None.
+    _watcher: Option<RecommendedWatcher>,
+    _keep_tx: Option<mpsc::UnboundedSender<()>>,
+    fs_rx: mpsc::UnboundedReceiver<()>,
+}
+
+impl ImessageSource {
+    pub fn new() -> Self {
+        let (watcher, fs_rx, keep_tx) = match start_watcher(&db_path()) {
+            Some((watcher, rx)) => (Some(watcher), rx, None),
+            None => {
+                let (tx, rx) = mpsc::unbounded_channel();
+                (None, rx, Some(tx))
+            }
+        };
+        Self {
+            cursors: None,
+            poll_now: state::chat_db_cursor().is_some(),
+            _watcher: watcher,
+            _keep_tx: keep_tx,
+            fs_rx,
+        }
+    }
+
+    /// The cursors, resolving them from chat.db on first use.
+    async fn cursors(&mut self) -> Result<(i64, i64), ChatDbError> {
+        if let Some(cursors) = self.cursors {
+            return Ok(cursors);
+        }
+        let saved = state::chat_db_cursor();
+        let max_age = i64::try_from(get_settings().chat_db.max_catch_up_secs).unwrap_or(i64::MAX);
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+        let cursors = tokio::task::spawn_blocking(move || {
+            with_reader(|reader| initial_cursors(reader, saved, now, max_age))
+        })
+        .await
+        .expect("initial_cursors task panicked")?;
+        info!(
+            initial_inbound_rowid = cursors.0,
+            initial_self_rowid = cursors.1,
+            resumed = saved.is_some(),
+            "iMessage source ready"
+        );
+        self.cursors = Some(cursors);
+        Ok(cursors)
+    }
+
+    async fn fetch(&mut self) -> Result<Batch<ChatDbCursor>, ChatDbError> {
+        let (inbound_rowid, self_rowid) = self.cursors().await?;
+        let (inbound, self_sent) =
+            tokio::task::spawn_blocking(move || fetch(inbound_rowid, self_rowid))
+                .await
+                .expect("chat.db fetch task panicked")?;
+        Ok(batch(inbound, self_sent, sent::is_own_message))
+    }
+}
+
+impl InboundSource for ImessageSource {
+    type Cursor = ChatDbCursor;
+
+    fn name(&self) -> &'static str {
+        "imessage"
+    }
+
+    async fn next_batch(&mut self) -> Option<Batch<ChatDbCursor>> {
+        loop {
+            if !std::mem::take(&mut self.poll_now) {
+                tokio::select! {
+                    _ = self.fs_rx.recv() => {
+                        while self.fs_rx.try_recv().is_ok() {}
+                    }
+                    // The timer restarts after every fs-triggered poll, which is
+                    // intentional: if fs events are flowing, we don't need the fallback.
+                    () = tokio::time::sleep(FALLBACK_POLL) => {}
+                }
+            }
+            match self.fetch().await {
+                Ok(batch) if !batch.is_empty() => return Some(batch),
+                Ok(_) => {}
+                Err(e) => warn!(error = %e, "chat.db poll failed"),
+            }
+        }
+    }
+
+    fn advance(&mut self, cursor: ChatDbCursor) {
+        let Some((inbound_rowid, self_rowid)) = &mut self.cursors else {
+            return;
+        };
+        match cursor {
+            ChatDbCursor::Inbound(rowid) => *inbound_rowid = rowid,
+            ChatDbCursor::SelfSent(rowid) => *self_rowid = rowid,
+            ChatDbCursor::Through { inbound, self_sent } => {
+                *inbound_rowid = inbound.unwrap_or(*inbound_rowid);
+                *self_rowid = self_sent.unwrap_or(*self_rowid);
+            }
+        }
+    }
+
+    /// Record the current cursors so a restart resumes from here.
+    async fn save_cursor(&mut self, store: &EventStore) {
+        let Some((inbound_rowid, self_rowid)) = self.cursors else {
+            return;
+        };
+        let cursor = ChatDbCursorSaved {
+            inbound_rowid,
+            self_rowid,
+        };
+        if state::chat_db_cursor() == Some(cursor) {
+            return;
+        }
+        if let Err(e) = append_chat_db_cursor_saved(store, &cursor).await {
+            warn!(error = %e, "failed to append ChatDbCursorSaved event");
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat_db::tests::fixture_db;
+
+    #[test]
+    fn apple_date_nanos_is_relative_to_2001() {
+        assert_eq!(apple_date_nanos(978_307_200), 0);
+        assert_eq!(apple_date_nanos(978_307_201), 1_000_000_000);
+        // 2024-01-01T00:00:00Z
+        assert_eq!(apple_date_nanos(1_704_067_200), 725_760_000_000_000_000);
+    }
+
+    #[test]
+    fn initial_cursors_resume_but_skip_stale_messages() {
+        let path = fixture_db();
+        let reader = Reader::open(&path, Duration::from_secs(1)).unwrap();
+        // 2025-01-01T00:01:05Z, when rowids 100 and 101 are a minute old.
+        let now = 1_735_689_665;
+        let saved = |inbound_rowid, self_rowid| {
+            Some(ChatDbCursorSaved {
+                inbound_rowid,
+                self_rowid,
+            })
+        };
+
+        assert_eq!(
+            initial_cursors(&reader, None, now, 3600).unwrap(),
+            (106, 106)
+        );
+        assert_eq!(
+            initial_cursors(&reader, saved(100, 99), now, 3600).unwrap(),
+            (100, 99)
+        );
+        assert_eq!(
+            initial_cursors(&reader, saved(100, 99), now, 1).unwrap(),
+            (101, 101)
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn batch_skips_own_messages_and_blank_rows_but_ends_after_them() {
+        let inbound = vec![
+            (1, "  [harold] ship it \n".to_string()),
+            (2, "   ".to_string()),
+        ];
+        let self_sent = vec![
+            (3, "🤖 [harold:0.1] Done (main)".to_string()),
+            (4, "✓ Delivered to [harold]".to_string()),
+            (5, "yes, really".to_string()),
+        ];
+        let batch = batch(inbound, self_sent, |rowid, _| rowid == 3 || rowid == 4);
+
+        let replies: Vec<_> = batch
+            .replies
+            .into_iter()
+            .map(|(cursor, reply)| (cursor, reply.text))
+            .collect();
+        assert_eq!(
+            replies,
+            vec![
+                (ChatDbCursor::Inbound(1), "[harold] ship it".to_string()),
+                (ChatDbCursor::SelfSent(5), "yes, really".to_string()),
+            ]
+        );
+        assert_eq!(
+            batch.end,
+            Some(ChatDbCursor::Through {
+                inbound: Some(2),
+                self_sent: Some(5),
+            })
+        );
+        assert!(super::batch(vec![], vec![], |_, _| false).is_empty());
+    }
+}
@@ -0,0 +1,452 @@
+use std::time::Duration;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use events::EventStore;
+use tracing::{info, warn};
+
+use super::{Batch, InboundSource};
+use crate::outbound::email::{Mailer, REPLY_TOKEN_HEADER, address};
+use crate::settings::get_settings;
+use crate::state;
+use crate::store::{EmailCursorSaved, ReplyReceived, append_email_cursor_saved};
+
+// ---------------------------------------------------------------------------
+// Message parsing — just enough MIME for replies from mail clients
+// ---------------------------------------------------------------------------
+
+/// Header block and body of a message or MIME part. Header names are lowercased.
+struct Part<'a> {
+    headers: Vec<(String, String)>,
+    body: &'a [u8],
+}
+
+impl<'a> Part<'a> {
+    fn parse(raw: &'a [u8]) -> Self {
+        let (head, body) = match find(raw, b"\r\n\r\n") {
+            Some(i) => (&raw[..i], &raw[i + 4..]),
+            None => match find(raw, b"\n\n") {
+                Some(i) => (&raw[..i], &raw[i + 2..]),
+                None => (raw, &raw[raw.len()..]),
+            },
+        };
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for line in String::from_utf8_lossy(head).lines() {
+            if line.starts_with([' ', '\t']) {
+                // Folded continuation of the previous header.
+                if let Some((_, value)) = headers.last_mut() {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+            } else if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+            }
+        }
+        Self { headers, body }
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// `(type/subtype, boundary)` from `Content-Type`; plain text when absent.
+    fn content_type(&self) -> (String, Option<String>) {
+        let Some(value) = self.header("content-type") else {
+            return ("text/plain".into(), None);
+        };
+        let mut params = value.split(';');
+        let mime = params.next().unwrap_or_default().trim().to_lowercase();
+        let boundary = params.find_map(|p| {
+            let (k, v) = p.split_once('=')?;
+            (k.trim().eq_ignore_ascii_case("boundary"))
+                .then(|| v.trim().trim_matches('"').to_string())
+        });
+        (mime, boundary)
+    }
+
+    fn decoded_body(&self) -> String {
+        let encoding = self
+            .header("content-transfer-encoding")
+            .unwrap_or_default()
+            .to_lowercase();
+        let bytes = match encoding.as_str() {
+            "base64" => {
+                let compact: Vec<u8> = self
+                    .body
+                    .iter()
+                    .copied()
+                    .filter(|b| !b.is_ascii_whitespace())
+                    .collect();
+                BASE64.decode(compact).unwrap_or_default()
+            }
+            "quoted-printable" => {
+                quoted_printable::decode(self.body, quoted_printable::ParseMode::Robust)
+                    .unwrap_or_default()
+            }
+            _ => self.body.to_vec(),
+        };
+        String::from_utf8_lossy(&bytes).replace("\r\n", "\n")
+    }
+
+    /// The first `text/plain` body, descending into multipart containers.
+    fn plain_text(&self) -> Option<String> {
+        let (mime, boundary) = self.content_type();
+        if mime.starts_with("multipart/") {
+            let delimiter = format!("--{}", boundary?);
+            return split_parts(self.body, delimiter.as_bytes())
+                .into_iter()
+                .find_map(|raw| Part::parse(raw).plain_text());
+        }
+        (mime == "text/plain").then(|| self.decoded_body())
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// The parts between `--boundary` delimiter lines, without the preamble.
+fn split_parts<'a>(body: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut parts = Vec::new();
+    let mut rest = body;
+    let mut started = false;
+    while let Some(i) = find(rest, delimiter) {
+        if started {
+            parts.push(rest[..i].strip_suffix(b"\r\n").unwrap_or(&rest[..i]));
+        }
+        started = true;
+        rest = &rest[i + delimiter.len()..];
+        if rest.starts_with(b"--") {
+            break;
+        }
+        let line_end = find(rest, b"\n").map_or(rest.len(), |i| i + 1);
+        rest = &rest[line_end..];
+    }
+    parts
+}
+
+// ---------------------------------------------------------------------------
+// Quoted text
+// ---------------------------------------------------------------------------
+
+/// Lines that start the quoted original or a signature; everything from here is dropped.
+fn starts_trailer(line: &str, next: Option<&str>) -> bool {
+    let t = line.trim();
+    t == "--"
+        || line == "-- "
+        || t.starts_with("-----Original Message-----")
+        || t.starts_with("________________")
+        || (t.starts_with("On ") && t.ends_with("wrote:"))
+        // Attribution wrapped onto a second line by the client.
+        || (t.starts_with("On ") && next.is_some_and(|n| n.trim().ends_with("wrote:")))
+        // Outlook's header block above the original.
+        || (t.starts_with("From: ") && next.is_some_and(|n| {
+            let n = n.trim();
+            n.starts_with("Sent: ") || n.starts_with("Date: ")
+        }))
+}
+
+/// The new text of a reply: quoted lines, the attribution line and everything
+/// after it, and the signature are removed.
+pub(crate) fn strip_quoted(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut kept = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if starts_trailer(line, lines.get(i + 1).copied()) {
+            break;
+        }
+        if !line.trim_start().starts_with('>') {
+            kept.push(line.trim_end());
+        }
+    }
+    kept.join("\n").trim().to_string()
+}
+
+// ---------------------------------------------------------------------------
+// Replies
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct InboundEmail {
+    pub from: String,
+    pub reply_token: Option<String>,
+    pub text: String,
+}
+
+/// Token from our header, a `<harold-TOKEN@…>` message id the reply refers to,
+/// or `[#TOKEN]` in the subject — in that order.
+fn reply_token(part: &Part) -> Option<String> {
+    if let Some(token) = part.header(&REPLY_TOKEN_HEADER.to_lowercase()) {
+        return Some(token.trim().to_string());
+    }
+    let from_ids = ["in-reply-to", "references"].iter().find_map(|h| {
+        let value = part.header(h)?;
+        let start = value.find("<harold-")? + "<harold-".len();
+        let end = start + value[start..].find('@')?;
+        Some(value[start..end].to_string())
+    });
+    from_ids.or_else(|| {
+        let subject = part.header("subject")?;
+        let start = subject.rfind("[#")? + 2;
+        let end = start + subject[start..].find(']')?;
+        Some(subject[start..end].to_string())
+    })
+}
+
+/// Whether `part` is a mail Harold sent: its own message id or reply-token
+/// header. When `email.from` and `email.to` are one mailbox these land in the
+/// polled folder from the allowed sender, and must not loop back to a pane.
+fn is_harolds(part: &Part) -> bool {
+    part.header("message-id")
+        .is_some_and(|id| id.trim().starts_with("<harold-"))
+        || part.header(&REPLY_TOKEN_HEADER.to_lowercase()).is_some()
+}
+
+/// The reply in `raw`, or `None` if it is unreadable or one of Harold's own.
+pub(crate) fn parse_reply(raw: &[u8]) -> Option<InboundEmail> {
+    let message = Part::parse(raw);
+    if is_harolds(&message) {
+        return None;
+    }
+    let from = address(message.header("from")?).to_lowercase();
+    let text = strip_quoted(&message.plain_text()?);
+    Some(InboundEmail {
+        from,
+        reply_token: reply_token(&message),
+        text,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// IMAP polling
+// ---------------------------------------------------------------------------
+
+/// New replies after `after` (all of them as `(uid, reply)`), or the highest
+/// UID with no replies on a first poll.
+fn fetch_new(mailer: &Mailer, after: Option<u32>) -> Vec<(u32, Option<InboundEmail>)> {
+    let uids = match mailer.search_after(after) {
+        Ok(uids) => uids,
+        Err(e) => {
+            warn!(error = %e, "IMAP search failed");
+            return vec![];
+        }
+    };
+    if after.is_none() {
+        return uids.into_iter().map(|uid| (uid, None)).collect();
+    }
+    let mut fetched = Vec::new();
+    for uid in uids {
+        match mailer.fetch(uid) {
+            Ok(raw) => fetched.push((uid, parse_reply(&raw))),
+            Err(e) => {
+                // Stop here so this UID is retried on the next poll.
+                warn!(uid, error = %e, "IMAP fetch failed");
+                break;
+            }
+        }
+    }
+    fetched
+}
+
+/// Replies from `allowed` (each confirmed by its UID), with the highest UID
+/// fetched as the batch end.
+fn batch(fetched: Vec<(u32, Option<InboundEmail>)>, allowed: Option<&str>) -> Batch<u32> {
+    let end = fetched.last().map(|&(uid, _)| uid);
+    let replies = fetched
+        .into_iter()
+        .filter_map(|(uid, reply)| Some((uid, reply?)))
+        .filter(|(uid, reply)| {
+            if allowed != Some(reply.from.as_str()) {
+                warn!(uid, from = %reply.from, "email from unexpected sender ignored");
+                return false;
+            }
+            !reply.text.is_empty()
+        })
+        .map(|(uid, reply)| {
+            let reply = ReplyReceived {
+                text: reply.text,
+                reply_token: reply.reply_token,
+            };
+            (uid, reply)
+        })
+        .collect();
+    Batch { replies, end }
+}
+
+/// Polls the IMAP folder every `email.poll_secs` for replies from the
+/// configured recipient. Resumes from the saved UID; on first run, starts
+/// after the newest message.
+pub(crate) struct EmailSource {
+    mailer: Mailer,
+    allowed: Option<String>,
+    after: Option<u32>,
+    saved: Option<u32>,
+    polled: bool,
+}
+
+impl EmailSource {
+    pub fn from_settings() -> Option<Self> {
+        let Some(mailer) = Mailer::from_settings() else {
+            warn!("email source not started: smtp_url or imap_url not configured");
+            return None;
+        };
+        let saved = state::email_uid();
+        info!(after = saved, "email source opened");
+        Some(Self {
+            mailer,
+            allowed: get_settings()
+                .email
+                .to
+                .as_deref()
+                .map(|to| address(to).to_lowercase()),
+            after: saved,
+            saved,
+            polled: false,
+        })
+    }
+}
+
+impl InboundSource for EmailSource {
+    type Cursor = u32;
+
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    async fn next_batch(&mut self) -> Option<Batch<u32>> {
+        let interval = Duration::from_secs(get_settings().email.poll_secs);
+        loop {
+            if std::mem::replace(&mut self.polled, true) {
+                tokio::time::sleep(interval).await;
+            }
+            let fetched = tokio::task::spawn_blocking({
+                let mailer = self.mailer.clone();
+                let after = self.after;
+                move || fetch_new(&mailer, after)
+            })
+            .await
+            .unwrap_or_else(|e| {
+                warn!(error = %e, "IMAP fetch task panicked");
+                vec![]
+            });
+            let batch = batch(fetched, self.allowed.as_deref());
+            if !batch.is_empty() {
+                return Some(batch);
+            }
+        }
+    }
+
+    fn advance(&mut self, uid: u32) {
+        self.after = Some(uid);
+    }
+
+    async fn save_cursor(&mut self, store: &EventStore) {
+        let Some(uid) = self.after.filter(|&uid| self.saved != Some(uid)) else {
+            return;
+        };
+        match append_email_cursor_saved(store, &EmailCursorSaved { uid }).await {
+            Ok(()) => self.saved = Some(uid),
+            Err(e) => warn!(error = %e, "failed to append EmailCursorSaved event"),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::email::Email;
+
+    const GMAIL_REPLY: &str = "From: Me <ME@example.com>\r\n\
+Subject: Re: [harold:0.1] main [#3f9a2c1b]\r\n\
+In-Reply-To: <harold-3f9a2c1b@example.com>\r\n\
+Content-Type: multipart/alternative; boundary=\"b1\"\r\n\
+\r\n\
+--b1\r\n\
+Content-Type: text/plain; charset=\"UTF-8\"\r\n\
+Content-Transfer-Encoding: quoted-printable\r\n\
+\r\n\
+Yes, push it =E2=80=94 and tag a release.\r\n\
+\r\n\
+On Mon, 6 Jan 2025 at 10:00, Harold <harold@example.com> wrote:\r\n\
+> Fixed the race.\r\n\
+>\r\n\
+> Should I push?\r\n\
+--b1\r\n\
+Content-Type: text/html; charset=\"UTF-8\"\r\n\
+\r\n\
+<div>Yes, push it</div>\r\n\
+--b1--\r\n";
+
+    #[test]
+    fn parse_reply_decodes_multipart_and_strips_quote() {
+        let reply = parse_reply(GMAIL_REPLY.as_bytes()).unwrap();
+        assert_eq!(reply.from, "me@example.com");
+        assert_eq!(reply.reply_token.as_deref(), Some("3f9a2c1b"));
+        assert_eq!(reply.text, "Yes, push it — and tag a release.");
+    }
+
+    #[test]
+    fn harolds_own_emails_are_not_replies() {
+        let notification = Email {
+            from: "me@example.com",
+            to: "me@example.com",
+            subject: "#3 [harold:0.1] main [#3f9a2c1b]".into(),
+            body: "Fixed the race.\n\nShould I push?".into(),
+            reply_token: Some("3f9a2c1b"),
+        };
+        let confirmation = Email {
+            from: "me@example.com",
+            to: "me@example.com",
+            subject: "Harold".into(),
+            body: "✓ Delivered to [harold:0.1]".into(),
+            reply_token: None,
+        };
+        for email in [notification, confirmation] {
+            let raw = email.render(time::OffsetDateTime::UNIX_EPOCH);
+            assert_eq!(parse_reply(raw.as_bytes()), None);
+        }
+        assert!(parse_reply(GMAIL_REPLY.as_bytes()).is_some());
+    }
+
+    #[test]
+    fn reply_token_falls_back_to_subject() {
+        let raw = b"From: me@example.com\nSubject: Re: [harold:0.1] main [#0badf00d]\n\nok\n";
+        let reply = parse_reply(raw).unwrap();
+        assert_eq!(reply.reply_token.as_deref(), Some("0badf00d"));
+        assert_eq!(reply.text, "ok");
+    }
+
+    #[test]
+    fn strip_quoted_handles_common_clients() {
+        assert_eq!(
+            strip_quoted("Run the tests first.\n\n> Should I push?\n"),
+            "Run the tests first."
+        );
+        assert_eq!(
+            strip_quoted(
+                "Go ahead.\n\nOn Mon, Jan 6, 2025 at 10:00 AM Harold\n<harold@example.com> wrote:\n> x"
+            ),
+            "Go ahead."
+        );
+        assert_eq!(
+            strip_quoted(
+                "Ship it\n\n________________________________\nFrom: Harold\nSent: Monday\n"
+            ),
+            "Ship it"
+        );
+        assert_eq!(strip_quoted("Thanks\n-- \nMe\nACME Corp"), "Thanks");
+    }
+
+    #[test]
+    fn base64_body_is_decoded() {
+        let raw = b"From: me@example.com\nContent-Transfer-Encoding: base64\n\naGkgdGhlcmU=\n";
+        assert_eq!(parse_reply(raw).unwrap().text, "hi there");
+    }
+}
@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use events::EventStore;
+use tracing::{info, warn};
+
+use super::{Batch, InboundSource};
+use crate::outbound::matrix::{Client, SyncResponse};
+use crate::settings::get_settings;
+use crate::state;
+use crate::store::{MatrixCursorSaved, ReplyReceived, append_matrix_cursor_saved};
+
+/// Pause after a failed `/sync` before polling again.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Drop the quoted fallback (`> <@user> …` lines) that clients prepend to the
+/// body of a rich reply.
+fn strip_reply_fallback(body: &str) -> &str {
+    let mut rest = body;
+    while rest.starts_with('>') {
+        match rest.split_once('\n') {
+            Some((_, tail)) => rest = tail,
+            None => return "",
+        }
+    }
+    rest
+}
+
+/// Text replies in `room_id` from `allowed_user` sent at or after `not_before_ms`,
+/// each with the notification it answers. Events Harold sent itself carry a
+/// transaction id and are skipped; senders other than `allowed_user` are dropped.
+pub(crate) fn replies(
+    sync: &SyncResponse,
+    room_id: &str,
+    allowed_user: &str,
+    not_before_ms: i64,
+) -> Vec<ReplyReceived> {
+    let Some(room) = sync.rooms.join.get(room_id) else {
+        return vec![];
+    };
+    room.timeline
+        .events
+        .iter()
+        .filter(|e| e.kind == "m.room.message" && e.unsigned.transaction_id.is_none())
+        .filter(|e| {
+            if e.sender != allowed_user {
+                warn!(sender = %e.sender, "Matrix message from unexpected sender ignored");
+                return false;
+            }
+            e.origin_server_ts >= not_before_ms
+        })
+        .filter(|e| e.content.msgtype.as_deref() == Some("m.text"))
+        .filter_map(|e| {
+            let text = strip_reply_fallback(e.content.body.as_deref()?).trim();
+            (!text.is_empty()).then(|| ReplyReceived {
+                text: text.to_string(),
+                reply_token: e.reply_token().map(String::from),
+            })
+        })
+        .collect()
+}
+
+/// Long-polls `/sync` for messages from the allowlisted user in the configured
+/// room. Resumes from the saved batch token; on first run, starts from the
+/// current position without replaying history.
+pub(crate) struct MatrixSource {
+    client: Client,
+    room_id: String,
+    allowed_user: String,
+    since: Option<String>,
+    saved: Option<String>,
+}
+
+impl MatrixSource {
+    pub fn from_settings() -> Option<Self> {
+        let cfg = &get_settings().matrix;
+        let (Some(client), Some(room_id), Some(allowed_user)) = (
+            Client::from_settings(),
+            cfg.room_id.clone(),
+            cfg.allowed_user.clone(),
+        ) else {
+            warn!("Matrix source not started: [matrix] settings incomplete");
+            return None;
+        };
+        let saved = state::matrix_since();
+        info!(since = ?saved, room_id = %room_id, "Matrix source opened");
+        Some(Self {
+            client,
+            room_id,
+            allowed_user,
+            since: saved.clone(),
+            saved,
+        })
+    }
+}
+
+impl InboundSource for MatrixSource {
+    /// A `/sync` batch token. Events within a batch cannot be resumed from, so
+    /// each reply is confirmed by the token the batch was fetched with.
+    type Cursor = String;
+
+    fn name(&self) -> &'static str {
+        "matrix"
+    }
+
+    async fn next_batch(&mut self) -> Option<Batch<String>> {
+        let cfg = &get_settings().matrix;
+        loop {
+            let first = self.since.is_none();
+            let poll = tokio::task::spawn_blocking({
+                let client = self.client.clone();
+                let room_id = self.room_id.clone();
+                let since = self.since.clone();
+                let timeout = if first { 0 } else { cfg.poll_timeout_secs };
+                move || client.sync(&room_id, since.as_deref(), timeout)
+            });
+            match poll.await {
+                Ok(Ok(sync)) => {
+                    let fetched_with = self.since.clone().unwrap_or_default();
+                    let found = if first {
+                        vec![]
+                    } else {
+                        let max_age = i64::try_from(cfg.max_catch_up_secs).unwrap_or(i64::MAX);
+                        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+                        let not_before_ms = now.saturating_sub(max_age).saturating_mul(1000);
+                        replies(&sync, &self.room_id, &self.allowed_user, not_before_ms)
+                    };
+                    let batch = Batch {
+                        replies: found
+                            .into_iter()
+                            .map(|r| (fetched_with.clone(), r))
+                            .collect(),
+                        end: (self.since.as_deref() != Some(sync.next_batch.as_str()))
+                            .then_some(sync.next_batch),
+                    };
+                    if !batch.is_empty() {
+                        return Some(batch);
+                    }
+                    continue;
+                }
+                Ok(Err(e)) => warn!(error = %e, "Matrix sync failed"),
+                Err(e) => warn!(error = %e, "Matrix sync task panicked"),
+            }
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+    }
+
+    fn advance(&mut self, next_batch: String) {
+        if !next_batch.is_empty() {
+            self.since = Some(next_batch);
+        }
+    }
+
+    async fn save_cursor(&mut self, store: &EventStore) {
+        let Some(next_batch) = self
+            .since
+            .clone()
+            .filter(|s| self.saved.as_ref() != Some(s))
+        else {
+            return;
+        };
+        let cursor = MatrixCursorSaved {
+            next_batch: next_batch.clone(),
+        };
+        match append_matrix_cursor_saved(store, &cursor).await {
+            Ok(()) => self.saved = Some(next_batch),
+            Err(e) => warn!(error = %e, "failed to append MatrixCursorSaved event"),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(sender: &str, ts: i64, content: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "event_id": "$e",
+            "sender": sender,
+            "type": "m.room.message",
+            "origin_server_ts": ts,
+            "content": content,
+        })
+    }
+
+    #[test]
+    fn replies_keep_recent_text_from_allowed_user_with_thread_root() {
+        let mut own = event(
+            "@me:hs",
+            5_000,
+            serde_json::json!({"msgtype": "m.text", "body": "[x] done"}),
+        );
+        own["unsigned"] = serde_json::json!({ "transaction_id": "t1" });
+        let sync: SyncResponse = serde_json::from_value(serde_json::json!({
+            "next_batch": "s9",
+            "rooms": { "join": { "!room:hs": { "timeline": { "events": [
+                event("@me:hs", 5_000, serde_json::json!({
+                    "msgtype": "m.text",
+                    "body": "ship it",
+                    "m.relates_to": { "rel_type": "m.thread", "event_id": "$root" },
+                })),
+                event("@me:hs", 5_000, serde_json::json!({
+                    "msgtype": "m.text",
+                    "body": "> <@harold:hs> Should I push?\n\nyes",
+                    "m.relates_to": { "m.in_reply_to": { "event_id": "$other" } },
+                })),
+                event("@me:hs", 5_000, serde_json::json!({"msgtype": "m.text", "body": "[harold] plain"})),
+                event("@eve:hs", 5_000, serde_json::json!({"msgtype": "m.text", "body": "spam"})),
+                event("@me:hs", 10, serde_json::json!({"msgtype": "m.text", "body": "too old"})),
+                event("@me:hs", 5_000, serde_json::json!({"msgtype": "m.image", "body": "cat.png"})),
+                own,
+            ] } } } },
+        }))
+        .unwrap();
+
+        let got: Vec<(String, Option<String>)> = replies(&sync, "!room:hs", "@me:hs", 1_000)
+            .into_iter()
+            .map(|r| (r.text, r.reply_token))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                ("ship it".to_string(), Some("$root".to_string())),
+                ("yes".to_string(), Some("$other".to_string())),
+                ("[harold] plain".to_string(), None),
+            ]
+        );
+        assert!(replies(&sync, "!elsewhere:hs", "@me:hs", 0).is_empty());
+    }
+}
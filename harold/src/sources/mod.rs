@@ -0,0 +1,177 @@
+pub mod chat_db;
+pub mod email;
+pub mod matrix;
+pub mod socket;
+pub mod stdin;
+pub mod telegram;
+
+use std::future::Future;
+use std::sync::Arc;
+
+use events::EventStore;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{Instrument, info, info_span, warn};
+
+use crate::settings::{AwayChannel, InboundSourceKind, get_settings};
+use crate::store::{ReplyReceived, append_reply_received};
+
+// ---------------------------------------------------------------------------
+// InboundSource — replies from a human, wherever they arrive
+// ---------------------------------------------------------------------------
+
+/// Replies fetched in one go. Each reply carries the cursor that confirms it;
+/// `end` confirms the whole batch, including anything the source filtered out.
+pub(crate) struct Batch<C> {
+    pub replies: Vec<(C, ReplyReceived)>,
+    pub end: Option<C>,
+}
+
+impl<C> Batch<C> {
+    pub fn is_empty(&self) -> bool {
+        self.replies.is_empty() && self.end.is_none()
+    }
+}
+
+/// A reply typed as one line (socket, stdin); blank lines are skipped.
+fn line_reply(line: &str) -> Option<ReplyReceived> {
+    let text = line.trim();
+    (!text.is_empty()).then(|| ReplyReceived {
+        text: text.to_string(),
+        reply_token: None,
+    })
+}
+
+/// A place replies come from. The source owns its cursor: the driver only
+/// reports how far appends got, and asks the source to persist it.
+pub(crate) trait InboundSource: Send + 'static {
+    type Cursor: Send;
+
+    /// Short name for logs, e.g. `chat_db`.
+    fn name(&self) -> &'static str;
+
+    /// Wait for the next non-empty batch. Dropped on shutdown, so it must not
+    /// move the cursor itself. `None` ends the source (e.g. stdin closed).
+    fn next_batch(&mut self) -> impl Future<Output = Option<Batch<Self::Cursor>>> + Send;
+
+    /// Everything up to `cursor` has been appended.
+    fn advance(&mut self, _cursor: Self::Cursor) {}
+
+    /// Record the cursor so a restart resumes after it. Sources without a
+    /// resume point (sockets, stdin) keep the default.
+    fn save_cursor(&mut self, _store: &EventStore) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+}
+
+/// Append a batch in order. The cursor stops at the first failed append so the
+/// rest of the batch is fetched again.
+async fn deliver<S: InboundSource>(source: &mut S, store: &EventStore, batch: Batch<S::Cursor>) {
+    let mut complete = true;
+    for (cursor, reply) in batch.replies {
+        let trace_id = uuid::Uuid::new_v4().to_string();
+        let span = info_span!("inbound", source = source.name(), trace_id = %trace_id);
+        let appended = async {
+            info!(reply_token = ?reply.reply_token, "reply received");
+            append_reply_received(store, &reply)
+                .await
+                .inspect_err(|e| warn!(error = %e, "failed to append ReplyReceived event"))
+                .is_ok()
+        }
+        .instrument(span)
+        .await;
+        if !appended {
+            complete = false;
+            break;
+        }
+        source.advance(cursor);
+    }
+    if complete && let Some(end) = batch.end {
+        source.advance(end);
+    }
+    source.save_cursor(store).await;
+}
+
+/// Drive `source` until shutdown or until it ends.
+pub(crate) async fn run<S: InboundSource>(
+    mut source: S,
+    store: Arc<EventStore>,
+    mut shutdown: watch::Receiver<()>,
+) {
+    let name = source.name();
+    info!(source = name, "inbound source started");
+    loop {
+        let batch = tokio::select! {
+            biased;
+
+            _ = shutdown.changed() => {
+                info!(source = name, "inbound source shutting down");
+                break;
+            }
+            batch = source.next_batch() => batch,
+        };
+        let Some(batch) = batch else {
+            info!(source = name, "inbound source ended");
+            break;
+        };
+        deliver(&mut source, &store, batch).await;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Startup
+// ---------------------------------------------------------------------------
+
+/// The away channel's own source, then `inbound.sources`, without duplicates.
+pub fn configured() -> Vec<InboundSourceKind> {
+    let cfg = get_settings();
+    let away = match cfg.notify.away_channel {
+        AwayChannel::Imessage => InboundSourceKind::Imessage,
+        AwayChannel::Telegram => InboundSourceKind::Telegram,
+        AwayChannel::Email => InboundSourceKind::Email,
+        AwayChannel::Matrix => InboundSourceKind::Matrix,
+    };
+    let mut kinds = vec![away];
+    for &kind in &cfg.inbound.sources {
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+    kinds
+}
+
+/// Start every configured source on its own task. Sources that are not fully
+/// configured log why and are left out.
+pub fn spawn_all(store: &Arc<EventStore>, shutdown: &watch::Receiver<()>) -> Vec<JoinHandle<()>> {
+    let mut handles = Vec::new();
+    for kind in configured() {
+        let store = Arc::clone(store);
+        let shutdown = shutdown.clone();
+        let handle = match kind {
+            InboundSourceKind::Imessage => {
+                tokio::spawn(run(chat_db::ImessageSource::new(), store, shutdown))
+            }
+            InboundSourceKind::Telegram => match telegram::TelegramSource::from_settings() {
+                Some(source) => tokio::spawn(run(source, store, shutdown)),
+                None => continue,
+            },
+            InboundSourceKind::Email => match email::EmailSource::from_settings() {
+                Some(source) => tokio::spawn(run(source, store, shutdown)),
+                None => continue,
+            },
+            InboundSourceKind::Matrix => match matrix::MatrixSource::from_settings() {
+                Some(source) => tokio::spawn(run(source, store, shutdown)),
+                None => continue,
+            },
+            InboundSourceKind::Socket => match socket::SocketSource::bind() {
+                Some(source) => tokio::spawn(run(source, store, shutdown)),
+                None => continue,
+            },
+            InboundSourceKind::Stdin => {
+                tokio::spawn(run(stdin::StdinSource::new(), store, shutdown))
+            }
+        };
+        handles.push(handle);
+    }
+    handles
+}
@@ -0,0 +1,149 @@
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use super::{Batch, InboundSource, line_reply};
+use crate::settings::get_settings;
+use crate::store::ReplyReceived;
+
+/// Replies written to a local Unix socket, one per line:
+/// `echo '[harold] run the tests' | nc -U ~/.harold/inbound.sock`.
+/// Any number of clients may connect; the socket is private to the user.
+pub(crate) struct SocketSource {
+    path: PathBuf,
+    rx: mpsc::Receiver<ReplyReceived>,
+}
+
+impl SocketSource {
+    /// Listen on `inbound.socket_path`, replacing a stale socket file.
+    pub fn bind() -> Option<Self> {
+        let path = PathBuf::from(get_settings().inbound.resolved_socket_path());
+        match Self::bind_at(path.clone()) {
+            Ok(source) => Some(source),
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "socket source not started");
+                None
+            }
+        }
+    }
+
+    fn bind_at(path: PathBuf) -> std::io::Result<Self> {
+        let parent = path.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(parent)?;
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        let listener = bind_private(parent, &path)?;
+        info!(path = %path.display(), "socket source listening");
+
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(accept(listener, tx));
+        Ok(Self { path, rx })
+    }
+}
+
+/// Bind inside a fresh 0700 directory, restrict the socket to 0600, then move
+/// it to `path`: whoever can write to it can type into agent panes, so it is
+/// never reachable with the process umask's permissions.
+fn bind_private(parent: &Path, path: &Path) -> std::io::Result<UnixListener> {
+    let staging = parent.join(format!(".harold-bind-{}", uuid::Uuid::new_v4().simple()));
+    std::fs::DirBuilder::new().mode(0o700).create(&staging)?;
+    let staged = staging.join("sock");
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_file(&staged);
+    let _ = std::fs::remove_dir(&staging);
+    bound
+}
+
+impl Drop for SocketSource {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Accept connections until the source is dropped.
+async fn accept(listener: UnixListener, tx: mpsc::Sender<ReplyReceived>) {
+    loop {
+        tokio::select! {
+            () = tx.closed() => return,
+            conn = listener.accept() => match conn {
+                Ok((stream, _)) => {
+                    tokio::spawn(read_lines(stream, tx.clone()));
+                }
+                Err(e) => warn!(error = %e, "socket source accept failed"),
+            },
+        }
+    }
+}
+
+async fn read_lines(stream: UnixStream, tx: mpsc::Sender<ReplyReceived>) {
+    let mut lines = BufReader::new(stream).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if let Some(reply) = line_reply(&line)
+            && tx.send(reply).await.is_err()
+        {
+            return;
+        }
+    }
+}
+
+impl InboundSource for SocketSource {
+    type Cursor = ();
+
+    fn name(&self) -> &'static str {
+        "socket"
+    }
+
+    async fn next_batch(&mut self) -> Option<Batch<()>> {
+        let mut replies = vec![((), self.rx.recv().await?)];
+        while let Ok(reply) = self.rx.try_recv() {
+            replies.push(((), reply));
+        }
+        Some(Batch { replies, end: None })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn lines_from_clients_become_replies() {
+        let path = std::env::temp_dir().join(format!("harold-{}.sock", uuid::Uuid::new_v4()));
+        let mut source = SocketSource::bind_at(path.clone()).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let mut client = UnixStream::connect(&path).await.unwrap();
+        client
+            .write_all(b"[harold] run the tests\n\nship it\n")
+            .await
+            .unwrap();
+        drop(client);
+
+        let mut texts = Vec::new();
+        while texts.len() < 2 {
+            let batch = source.next_batch().await.unwrap();
+            texts.extend(batch.replies.into_iter().map(|(_, r)| r.text));
+        }
+        assert_eq!(texts, vec!["[harold] run the tests", "ship it"]);
+
+        drop(source);
+        assert!(!path.exists(), "socket file removed on drop");
+    }
+}
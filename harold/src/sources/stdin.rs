@@ -0,0 +1,66 @@
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader, Lines, Stdin};
+
+use super::{Batch, InboundSource, line_reply};
+
+/// One reply per line of standard input, for exercising routing by hand:
+/// `harold` reads `[harold] run the tests` as if it had arrived on the away
+/// channel. The source ends at EOF.
+pub(crate) struct StdinSource<R = BufReader<Stdin>> {
+    lines: Lines<R>,
+}
+
+impl StdinSource {
+    pub fn new() -> Self {
+        Self::from_reader(BufReader::new(tokio::io::stdin()))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> StdinSource<R> {
+    pub fn from_reader(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+        }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin + Send + 'static> InboundSource for StdinSource<R> {
+    type Cursor = ();
+
+    fn name(&self) -> &'static str {
+        "stdin"
+    }
+
+    async fn next_batch(&mut self) -> Option<Batch<()>> {
+        // `next_line` is cancel-safe, so shutdown never loses half a line.
+        while let Ok(Some(line)) = self.lines.next_line().await {
+            if let Some(reply) = line_reply(&line) {
+                return Some(Batch {
+                    replies: vec![((), reply)],
+                    end: None,
+                });
+            }
+        }
+        None
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn each_non_blank_line_is_a_reply_until_eof() {
+        let input: &[u8] = b"[harold] run the tests\n\n   \nship it\r\n";
+        let mut source = StdinSource::from_reader(input);
+
+        let mut texts = Vec::new();
+        while let Some(batch) = source.next_batch().await {
+            texts.extend(batch.replies.into_iter().map(|(_, r)| r.text));
+        }
+        assert_eq!(texts, vec!["[harold] run the tests", "ship it"]);
+    }
+}
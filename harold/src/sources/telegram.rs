@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use events::EventStore;
+use tracing::{info, warn};
+
+use super::{Batch, InboundSource};
+use crate::outbound::telegram::{Bot, Update};
+use crate::settings::get_settings;
+use crate::state;
+use crate::store::{ReplyReceived, TelegramCursorSaved, append_telegram_cursor_saved};
+
+/// Pause after a failed `getUpdates` before polling again.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Text replies from `chat_id` no older than `not_before` (Unix seconds), each
+/// with the offset that confirms it, and the offset that confirms every update
+/// in the batch. Messages from other chats are dropped — anyone can message a bot.
+pub(crate) fn replies(updates: &[Update], chat_id: i64, not_before: i64) -> Batch<i64> {
+    let end = updates.iter().map(|u| u.update_id + 1).max();
+    let replies = updates
+        .iter()
+        .filter_map(|u| Some((u.update_id + 1, u.message.as_ref()?)))
+        .filter(|(_, m)| {
+            if m.chat.id != chat_id {
+                warn!(
+                    chat_id = m.chat.id,
+                    "Telegram message from unexpected chat ignored"
+                );
+                return false;
+            }
+            m.date >= not_before
+        })
+        .filter_map(|(offset, m)| Some((offset, m.text.as_deref()?.trim())))
+        .filter(|(_, t)| !t.is_empty())
+        .map(|(offset, text)| {
+            let reply = ReplyReceived {
+                text: text.to_string(),
+                reply_token: None,
+            };
+            (offset, reply)
+        })
+        .collect();
+    Batch { replies, end }
+}
+
+/// Long-polls `getUpdates` for replies from the configured chat. Resumes from
+/// the saved offset; on first run Telegram returns whatever is still pending,
+/// filtered by `telegram.max_catch_up_secs`.
+pub(crate) struct TelegramSource {
+    bot: Bot,
+    chat_id: i64,
+    offset: i64,
+    saved: Option<i64>,
+}
+
+impl TelegramSource {
+    pub fn from_settings() -> Option<Self> {
+        let (Some(bot), Some(chat_id)) = (Bot::from_settings(), get_settings().telegram.chat_id)
+        else {
+            warn!("Telegram source not started: bot_token or chat_id not configured");
+            return None;
+        };
+        let saved = state::telegram_offset();
+        info!(offset = saved, chat_id, "Telegram source opened");
+        Some(Self {
+            bot,
+            chat_id,
+            offset: saved.unwrap_or(0),
+            saved,
+        })
+    }
+}
+
+impl InboundSource for TelegramSource {
+    type Cursor = i64;
+
+    fn name(&self) -> &'static str {
+        "telegram"
+    }
+
+    async fn next_batch(&mut self) -> Option<Batch<i64>> {
+        let cfg = &get_settings().telegram;
+        loop {
+            let poll = tokio::task::spawn_blocking({
+                let bot = self.bot.clone();
+                let (offset, timeout) = (self.offset, cfg.poll_timeout_secs);
+                move || bot.get_updates(offset, timeout)
+            });
+            match poll.await {
+                Ok(Ok(updates)) => {
+                    let max_age = i64::try_from(cfg.max_catch_up_secs).unwrap_or(i64::MAX);
+                    let now = time::OffsetDateTime::now_utc().unix_timestamp();
+                    let batch = replies(&updates, self.chat_id, now.saturating_sub(max_age));
+                    if !batch.is_empty() {
+                        return Some(batch);
+                    }
+                    continue;
+                }
+                Ok(Err(e)) => warn!(error = %e, "Telegram getUpdates failed"),
+                Err(e) => warn!(error = %e, "Telegram poll task panicked"),
+            }
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+    }
+
+    fn advance(&mut self, offset: i64) {
+        self.offset = offset;
+    }
+
+    async fn save_cursor(&mut self, store: &EventStore) {
+        if self.saved == Some(self.offset) {
+            return;
+        }
+        let cursor = TelegramCursorSaved {
+            offset: self.offset,
+        };
+        match append_telegram_cursor_saved(store, &cursor).await {
+            Ok(()) => self.saved = Some(self.offset),
+            Err(e) => warn!(error = %e, "failed to append TelegramCursorSaved event"),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(update_id: i64, chat_id: i64, date: i64, text: Option<&str>) -> Update {
+        serde_json::from_value(serde_json::json!({
+            "update_id": update_id,
+            "message": { "chat": { "id": chat_id }, "date": date, "text": text },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn replies_keep_only_recent_text_from_configured_chat() {
+        let updates = [
+            update(10, 42, 1_000, Some("[harold] ship it")),
+            update(11, 99, 1_000, Some("spam")),
+            update(12, 42, 1_000, None),
+            update(13, 42, 10, Some("too old")),
+            update(14, 42, 1_000, Some("  ")),
+        ];
+        let batch = replies(&updates, 42, 500);
+        let texts: Vec<(i64, &str)> = batch
+            .replies
+            .iter()
+            .map(|(offset, r)| (*offset, r.text.as_str()))
+            .collect();
+        assert_eq!(texts, vec![(11, "[harold] ship it")]);
+        // Dropped updates are still confirmed, so they are not redelivered.
+        assert_eq!(batch.end, Some(15));
+        assert!(replies(&[], 42, 0).is_empty());
+    }
+}
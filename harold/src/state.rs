@@ -0,0 +1,676 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::{LazyLock, RwLock};
+
+use events::EventStore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::OffsetDateTime;
+use tracing::{info, warn};
+
+use crate::inbound::AgentAddress;
+use crate::store::{
+    AgentMessageSent, AwayNotificationSent, ChatDbCursorSaved, ControlKeyRequested,
+    EmailCursorSaved, ImessageSent, ManualPresenceSet, MatrixCursorSaved, PaneHandleAssigned,
+    Presence, ReplyLaneStarted, ReplyRouted, TelegramCursorSaved, TurnCompleted, read_events,
+    set_next_version,
+};
+
+// ---------------------------------------------------------------------------
+// State — read model folded from harold.events
+// ---------------------------------------------------------------------------
+
+static STATE: LazyLock<RwLock<State>> = LazyLock::new(|| RwLock::new(State::default()));
+
+/// Reply tokens remembered; older ones are forgotten, and a reply naming one
+/// falls back to the other routing methods.
+const REPLY_TOKENS_KEPT: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastTurn {
+    pub at: OffsetDateTime,
+    pub last_user_prompt: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct State {
+    last_turns: HashMap<String, LastTurn>,
+    /// When each pane was last sent a reply or an RPC message.
+    last_input_at: HashMap<String, OffsetDateTime>,
+    /// The agent whose turn last triggered an away notification.
+    last_away_notification_source_agent: Option<AgentAddress>,
+    /// The pane each `#handle` names.
+    pane_handles: BTreeMap<u32, AgentAddress>,
+    /// Agent of each away notification that carried a reply token.
+    reply_tokens: HashMap<String, AgentAddress>,
+    /// Keys of `reply_tokens`, oldest first, to bound it.
+    reply_token_order: VecDeque<String>,
+    /// Where the chat.db listener resumes polling.
+    chat_db_cursor: Option<ChatDbCursorSaved>,
+    /// chat.db rowids of Harold's own iMessages not yet behind the self cursor.
+    sent_imessages: BTreeSet<i64>,
+    /// Where the email listener resumes IMAP polling.
+    email_uid: Option<u32>,
+    /// Where the Telegram listener resumes long polling.
+    telegram_offset: Option<i64>,
+    /// Where the Matrix listener resumes `/sync`.
+    matrix_since: Option<String>,
+    /// The control key awaiting `!yes`, and when it was asked for.
+    pending_control: Option<(ControlKeyRequested, OffsetDateTime)>,
+    /// Presence override from `ManualPresenceSet`; `None` means automatic.
+    manual_presence: Option<Presence>,
+    /// First stream version the reply lane routes, from `ReplyLaneStarted`.
+    reply_lane_from: Option<u64>,
+}
+
+impl State {
+    fn remember_reply_token(&mut self, token: String, agent: AgentAddress) {
+        if self.reply_tokens.insert(token.clone(), agent).is_none() {
+            self.reply_token_order.push_back(token);
+        }
+        while self.reply_token_order.len() > REPLY_TOKENS_KEPT {
+            if let Some(oldest) = self.reply_token_order.pop_front() {
+                self.reply_tokens.remove(&oldest);
+            }
+        }
+    }
+
+    /// Fold one event into the read model. Unknown types are ignored.
+    pub fn apply(&mut self, event_type: &str, payload: &Value, at: OffsetDateTime) {
+        match event_type {
+            "TurnCompleted" => match serde_json::from_value::<TurnCompleted>(payload.clone()) {
+                Ok(turn) => {
+                    self.last_turns.insert(
+                        turn.pane_id,
+                        LastTurn {
+                            at,
+                            last_user_prompt: turn.last_user_prompt,
+                        },
+                    );
+                }
+                Err(e) => warn!(error = %e, "state: failed to deserialise TurnCompleted"),
+            },
+            "ReplyRouted" => match serde_json::from_value::<ReplyRouted>(payload.clone()) {
+                Ok(routed) => {
+                    self.last_input_at.insert(routed.pane_id, at);
+                }
+                Err(e) => warn!(error = %e, "state: failed to deserialise ReplyRouted"),
+            },
+            "AgentMessageSent" => match serde_json::from_value::<AgentMessageSent>(payload.clone())
+            {
+                Ok(sent) => {
+                    self.last_input_at.insert(sent.pane_id, at);
+                }
+                Err(e) => warn!(error = %e, "state: failed to deserialise AgentMessageSent"),
+            },
+            "PaneHandleAssigned" => {
+                match serde_json::from_value::<PaneHandleAssigned>(payload.clone()) {
+                    Ok(assigned) => {
+                        self.pane_handles
+                            .retain(|_, agent| agent.pane_id() != assigned.pane_id);
+                        let agent = AgentAddress::TmuxPane {
+                            pane_id: assigned.pane_id,
+                            label: assigned.pane_label,
+                        };
+                        self.pane_handles.insert(assigned.handle, agent);
+                    }
+                    Err(e) => warn!(error = %e, "state: failed to deserialise PaneHandleAssigned"),
+                }
+            }
+            "ControlKeyRequested" => {
+                match serde_json::from_value::<ControlKeyRequested>(payload.clone()) {
+                    Ok(request) => self.pending_control = Some((request, at)),
+                    Err(e) => warn!(error = %e, "state: failed to deserialise ControlKeyRequested"),
+                }
+            }
+            "ControlKeySent" | "ControlKeyCancelled" => self.pending_control = None,
+            "AwayNotificationSent" => {
+                match serde_json::from_value::<AwayNotificationSent>(payload.clone()) {
+                    Ok(sent) => {
+                        let agent = AgentAddress::TmuxPane {
+                            pane_id: sent.pane_id,
+                            label: sent.pane_label,
+                        };
+                        for token in sent.reply_token.into_iter().chain(sent.extra_reply_tokens) {
+                            self.remember_reply_token(token, agent.clone());
+                        }
+                        self.last_away_notification_source_agent = Some(agent);
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "state: failed to deserialise AwayNotificationSent")
+                    }
+                }
+            }
+            "ChatDbCursorSaved" => {
+                match serde_json::from_value::<ChatDbCursorSaved>(payload.clone()) {
+                    Ok(cursor) => {
+                        self.sent_imessages
+                            .retain(|&rowid| rowid > cursor.self_rowid);
+                        self.chat_db_cursor = Some(cursor);
+                    }
+                    Err(e) => warn!(error = %e, "state: failed to deserialise ChatDbCursorSaved"),
+                }
+            }
+            "ImessageSent" => match serde_json::from_value::<ImessageSent>(payload.clone()) {
+                Ok(sent) => {
+                    self.sent_imessages.insert(sent.rowid);
+                }
+                Err(e) => warn!(error = %e, "state: failed to deserialise ImessageSent"),
+            },
+            "EmailCursorSaved" => {
+                match serde_json::from_value::<EmailCursorSaved>(payload.clone()) {
+                    Ok(cursor) => self.email_uid = Some(cursor.uid),
+                    Err(e) => warn!(error = %e, "state: failed to deserialise EmailCursorSaved"),
+                }
+            }
+            "TelegramCursorSaved" => {
+                match serde_json::from_value::<TelegramCursorSaved>(payload.clone()) {
+                    Ok(cursor) => self.telegram_offset = Some(cursor.offset),
+                    Err(e) => warn!(error = %e, "state: failed to deserialise TelegramCursorSaved"),
+                }
+            }
+            "MatrixCursorSaved" => {
+                match serde_json::from_value::<MatrixCursorSaved>(payload.clone()) {
+                    Ok(cursor) => self.matrix_since = Some(cursor.next_batch),
+                    Err(e) => warn!(error = %e, "state: failed to deserialise MatrixCursorSaved"),
+                }
+            }
+            "ReplyLaneStarted" => {
+                match serde_json::from_value::<ReplyLaneStarted>(payload.clone()) {
+                    Ok(started) => self.reply_lane_from = Some(started.from_version),
+                    Err(e) => warn!(error = %e, "state: failed to deserialise ReplyLaneStarted"),
+                }
+            }
+            "ManualPresenceSet" => {
+                match serde_json::from_value::<ManualPresenceSet>(payload.clone()) {
+                    Ok(set) => self.manual_presence = set.presence,
+                    Err(e) => warn!(error = %e, "state: failed to deserialise ManualPresenceSet"),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn last_turn(&self, pane_id: &str) -> Option<&LastTurn> {
+        self.last_turns.get(pane_id)
+    }
+
+    /// The pane's handle and the label it was assigned under.
+    pub fn pane_handle(&self, pane_id: &str) -> Option<(u32, &str)> {
+        self.pane_handles
+            .iter()
+            .find(|(_, agent)| agent.pane_id() == pane_id)
+            .map(|(&handle, agent)| (handle, agent.label()))
+    }
+
+    /// The smallest handle not held by one of `live_pane_ids`.
+    pub fn free_handle(&self, live_pane_ids: &[&str]) -> u32 {
+        (1..)
+            .find(|handle| {
+                self.pane_handles
+                    .get(handle)
+                    .is_none_or(|agent| !live_pane_ids.contains(&agent.pane_id()))
+            })
+            .unwrap_or_default()
+    }
+
+    /// When the pane's last turn finished, if nothing has been sent to it since.
+    pub fn waiting_since(&self, pane_id: &str) -> Option<OffsetDateTime> {
+        let turn = self.last_turns.get(pane_id)?;
+        let answered = self
+            .last_input_at
+            .get(pane_id)
+            .is_some_and(|&input| input >= turn.at);
+        (!answered).then_some(turn.at)
+    }
+}
+
+/// Apply a freshly appended event to the global read model.
+pub(crate) fn apply(event_type: &str, payload: &Value, at: OffsetDateTime) {
+    STATE.write().unwrap().apply(event_type, payload, at);
+}
+
+pub(crate) fn last_turn(pane_id: &str) -> Option<LastTurn> {
+    STATE.read().unwrap().last_turn(pane_id).cloned()
+}
+
+pub(crate) fn pane_handle(pane_id: &str) -> Option<(u32, String)> {
+    let state = STATE.read().unwrap();
+    state
+        .pane_handle(pane_id)
+        .map(|(handle, label)| (handle, label.to_string()))
+}
+
+pub(crate) fn free_handle(live_pane_ids: &[&str]) -> u32 {
+    STATE.read().unwrap().free_handle(live_pane_ids)
+}
+
+pub(crate) fn agent_for_handle(handle: u32) -> Option<AgentAddress> {
+    STATE.read().unwrap().pane_handles.get(&handle).cloned()
+}
+
+pub(crate) fn waiting_since(pane_id: &str) -> Option<OffsetDateTime> {
+    STATE.read().unwrap().waiting_since(pane_id)
+}
+
+pub(crate) fn last_away_notification_source_agent() -> Option<AgentAddress> {
+    STATE
+        .read()
+        .unwrap()
+        .last_away_notification_source_agent
+        .clone()
+}
+
+pub(crate) fn chat_db_cursor() -> Option<ChatDbCursorSaved> {
+    STATE.read().unwrap().chat_db_cursor
+}
+
+pub(crate) fn is_sent_imessage(rowid: i64) -> bool {
+    STATE.read().unwrap().sent_imessages.contains(&rowid)
+}
+
+pub(crate) fn agent_for_reply_token(token: &str) -> Option<AgentAddress> {
+    STATE.read().unwrap().reply_tokens.get(token).cloned()
+}
+
+pub(crate) fn email_uid() -> Option<u32> {
+    STATE.read().unwrap().email_uid
+}
+
+pub(crate) fn telegram_offset() -> Option<i64> {
+    STATE.read().unwrap().telegram_offset
+}
+
+pub(crate) fn matrix_since() -> Option<String> {
+    STATE.read().unwrap().matrix_since.clone()
+}
+
+pub(crate) fn pending_control() -> Option<(ControlKeyRequested, OffsetDateTime)> {
+    STATE.read().unwrap().pending_control.clone()
+}
+
+pub(crate) fn manual_presence() -> Option<Presence> {
+    STATE.read().unwrap().manual_presence
+}
+
+pub(crate) fn reply_lane_from() -> Option<u64> {
+    STATE.read().unwrap().reply_lane_from
+}
+
+#[cfg(test)]
+pub(crate) fn set_last_away_notification_source_agent(addr: Option<AgentAddress>) {
+    STATE.write().unwrap().last_away_notification_source_agent = addr;
+}
+
+// ---------------------------------------------------------------------------
+// Snapshot — so startup replays only the events after it
+// ---------------------------------------------------------------------------
+
+/// Bump whenever `State` or `apply` changes, so an older snapshot is replaced
+/// by a full replay rather than missing what the new fields would have folded.
+const SNAPSHOT_FORMAT: u32 = 3;
+
+/// `S` is `State` when loading and `&State` when saving.
+#[derive(Serialize, Deserialize)]
+struct Snapshot<S> {
+    format: u32,
+    /// The stream version the snapshot was taken before.
+    next_version: u64,
+    /// Id of the event at `next_version - 1`, to detect a replaced store.
+    last_event_id: Option<uuid::Uuid>,
+    state: S,
+}
+
+/// Where the read model snapshot for the store at `store_path` lives.
+pub fn snapshot_path(store_path: &str) -> String {
+    format!("{store_path}.state.json")
+}
+
+/// The id of the event just before `next_version`.
+async fn last_event_id(
+    store: &EventStore,
+    next_version: u64,
+) -> events::Result<Option<uuid::Uuid>> {
+    let Some(last) = next_version.checked_sub(1) else {
+        return Ok(None);
+    };
+    Ok(read_events(store, last, 1).await?.first().map(|e| e.id))
+}
+
+/// The snapshot at `path`, if it was written by this format for this store.
+async fn load_snapshot(store: &EventStore, path: &str) -> Option<Snapshot<State>> {
+    let bytes = std::fs::read(path).ok()?;
+    let snapshot: Snapshot<State> = serde_json::from_slice(&bytes)
+        .inspect_err(|e| warn!(error = %e, path, "state snapshot unreadable; replaying"))
+        .ok()?;
+    if snapshot.format != SNAPSHOT_FORMAT {
+        info!(path, "state snapshot has an older format; replaying");
+        return None;
+    }
+    let id = last_event_id(store, snapshot.next_version).await.ok()?;
+    if id != snapshot.last_event_id {
+        warn!(
+            path,
+            "state snapshot does not match the event store; replaying"
+        );
+        return None;
+    }
+    Some(snapshot)
+}
+
+/// Write the read model and the stream head to `path`. Called at startup and
+/// on shutdown, once nothing else appends.
+pub async fn save_snapshot(store: &EventStore, path: &str) {
+    let next_version = crate::store::next_version();
+    let last_event_id = match last_event_id(store, next_version).await {
+        Ok(id) => id,
+        Err(e) => {
+            warn!(error = %e, "state snapshot skipped: event store read failed");
+            return;
+        }
+    };
+    let json = serde_json::to_vec(&Snapshot {
+        format: SNAPSHOT_FORMAT,
+        next_version,
+        last_event_id,
+        state: &*STATE.read().unwrap(),
+    });
+    let tmp = format!("{path}.tmp");
+    let written = json
+        .map_err(std::io::Error::from)
+        .and_then(|json| std::fs::write(&tmp, json))
+        .and_then(|()| std::fs::rename(&tmp, path));
+    if let Err(e) = written {
+        warn!(error = %e, path, "failed to write state snapshot");
+    }
+}
+
+/// Load the read model from the snapshot at `snapshot_path` and replay the
+/// events after it, or the whole stream without one. Called once at startup,
+/// before any task appends events.
+pub async fn rebuild(store: &EventStore, snapshot_path: &str) -> events::Result<()> {
+    const PAGE: usize = 512;
+    let (mut state, mut next) = match load_snapshot(store, snapshot_path).await {
+        Some(snapshot) => (snapshot.state, snapshot.next_version),
+        None => (State::default(), 0),
+    };
+    let from = next;
+    loop {
+        let page = read_events(store, next, PAGE).await?;
+        for e in &page {
+            state.apply(&e.r#type, &e.payload, e.timestamp);
+            next = e.version + 1;
+        }
+        if page.len() < PAGE {
+            break;
+        }
+    }
+    info!(from, events = next, "state rebuilt from event store");
+    *STATE.write().unwrap() = state;
+    set_next_version(next);
+    save_snapshot(store, snapshot_path).await;
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use time::OffsetDateTime;
+
+    use super::{REPLY_TOKENS_KEPT, SNAPSHOT_FORMAT, Snapshot, State};
+    use crate::store::Presence;
+
+    fn turn(pane_id: &str, prompt: &str) -> serde_json::Value {
+        json!({
+            "pane_id": pane_id,
+            "pane_label": "work:0.0",
+            "last_user_prompt": prompt,
+            "assistant_message": "done",
+            "main_context": "main",
+        })
+    }
+
+    #[test]
+    fn apply_turn_completed_tracks_latest_per_pane() {
+        let mut state = State::default();
+        let t0 = OffsetDateTime::UNIX_EPOCH;
+        let t1 = t0 + time::Duration::minutes(5);
+        state.apply("TurnCompleted", &turn("%1", "first"), t0);
+        state.apply("TurnCompleted", &turn("%1", "second"), t1);
+        state.apply("TurnCompleted", &turn("%2", "other"), t0);
+
+        let last = state.last_turn("%1").unwrap();
+        assert_eq!(last.last_user_prompt, "second");
+        assert_eq!(last.at, t1);
+        assert_eq!(state.last_turn("%2").unwrap().last_user_prompt, "other");
+    }
+
+    #[test]
+    fn waiting_since_clears_once_the_pane_is_answered() {
+        let mut state = State::default();
+        let t0 = OffsetDateTime::UNIX_EPOCH;
+        let t1 = t0 + time::Duration::minutes(5);
+        let turn = json!({
+            "pane_id": "%1",
+            "pane_label": "work:0.0",
+            "last_user_prompt": "p",
+            "assistant_message": "Shall I deploy?",
+            "main_context": "main",
+        });
+        state.apply("TurnCompleted", &turn, t0);
+        assert_eq!(state.waiting_since("%1"), Some(t0));
+        state.apply(
+            "ReplyRouted",
+            &json!({ "trace_id": "t", "pane_id": "%1", "label": "work:0.0", "method": "exact_tag" }),
+            t1,
+        );
+        assert_eq!(state.waiting_since("%1"), None);
+        assert_eq!(state.waiting_since("%2"), None);
+    }
+
+    #[test]
+    fn apply_away_notification_sent_sets_routing_target() {
+        let mut state = State::default();
+        let now = OffsetDateTime::UNIX_EPOCH;
+        state.apply(
+            "AwayNotificationSent",
+            &json!({ "pane_id": "%1", "pane_label": "work:0.0" }),
+            now,
+        );
+        state.apply(
+            "AwayNotificationSent",
+            &json!({ "pane_id": "%3", "pane_label": "alir-app:0.1" }),
+            now,
+        );
+        let last = state.last_away_notification_source_agent.as_ref().unwrap();
+        assert_eq!(last.pane_id(), "%3");
+        assert_eq!(last.label(), "alir-app:0.1");
+    }
+
+    #[test]
+    fn apply_away_notification_sent_remembers_reply_tokens() {
+        let mut state = State::default();
+        let now = OffsetDateTime::UNIX_EPOCH;
+        state.apply(
+            "AwayNotificationSent",
+            &json!({ "pane_id": "%1", "pane_label": "work:0.0", "reply_token": "3f9a2c1b" }),
+            now,
+        );
+        state.apply(
+            "AwayNotificationSent",
+            &json!({
+                "pane_id": "%2",
+                "pane_label": "api:0.0",
+                "reply_token": "$root",
+                "extra_reply_tokens": ["$question"],
+            }),
+            now,
+        );
+        state.apply(
+            "AwayNotificationSent",
+            &json!({ "pane_id": "%3", "pane_label": "alir-app:0.1" }),
+            now,
+        );
+        // A later notification moves the fallback target but not the token.
+        assert_eq!(state.reply_tokens["3f9a2c1b"].pane_id(), "%1");
+        assert_eq!(state.reply_tokens["$question"].pane_id(), "%2");
+        assert_eq!(
+            state.last_away_notification_source_agent.unwrap().pane_id(),
+            "%3"
+        );
+    }
+
+    #[test]
+    fn reply_tokens_forget_the_oldest_beyond_the_limit() {
+        let mut state = State::default();
+        let now = OffsetDateTime::UNIX_EPOCH;
+        for n in 0..=REPLY_TOKENS_KEPT {
+            state.apply(
+                "AwayNotificationSent",
+                &json!({ "pane_id": "%1", "pane_label": "work:0.0", "reply_token": format!("t{n}") }),
+                now,
+            );
+        }
+        assert_eq!(state.reply_tokens.len(), REPLY_TOKENS_KEPT);
+        assert_eq!(state.reply_token_order.len(), REPLY_TOKENS_KEPT);
+        assert!(!state.reply_tokens.contains_key("t0"));
+        assert!(state.reply_tokens.contains_key("t1"));
+        assert!(
+            state
+                .reply_tokens
+                .contains_key(&format!("t{REPLY_TOKENS_KEPT}"))
+        );
+    }
+
+    #[test]
+    fn apply_chat_db_cursor_saved_keeps_latest() {
+        let mut state = State::default();
+        let now = OffsetDateTime::UNIX_EPOCH;
+        state.apply(
+            "ChatDbCursorSaved",
+            &json!({ "inbound_rowid": 10, "self_rowid": 12 }),
+            now,
+        );
+        state.apply(
+            "ChatDbCursorSaved",
+            &json!({ "inbound_rowid": 15, "self_rowid": 12 }),
+            now,
+        );
+        let cursor = state.chat_db_cursor.unwrap();
+        assert_eq!((cursor.inbound_rowid, cursor.self_rowid), (15, 12));
+    }
+
+    #[test]
+    fn apply_imessage_sent_is_forgotten_behind_the_self_cursor() {
+        let mut state = State::default();
+        let now = OffsetDateTime::UNIX_EPOCH;
+        for rowid in [20, 30] {
+            state.apply(
+                "ImessageSent",
+                &json!({ "rowid": rowid, "guid": format!("G-{rowid}") }),
+                now,
+            );
+        }
+        state.apply(
+            "ChatDbCursorSaved",
+            &json!({ "inbound_rowid": 40, "self_rowid": 25 }),
+            now,
+        );
+        assert_eq!(state.sent_imessages.iter().collect::<Vec<_>>(), [&30]);
+    }
+
+    #[test]
+    fn apply_control_key_request_is_pending_until_sent_or_cancelled() {
+        let mut state = State::default();
+        let now = OffsetDateTime::UNIX_EPOCH;
+        let request =
+            |label| json!({ "trace_id": "t", "pane_id": "%1", "label": label, "key": "interrupt" });
+        state.apply("ControlKeyRequested", &request("work:0.0"), now);
+        state.apply("ControlKeyRequested", &request("work:0.1"), now);
+        let (pending, at) = state.pending_control.clone().unwrap();
+        assert_eq!((pending.label.as_str(), at), ("work:0.1", now));
+        state.apply("ControlKeyCancelled", &json!({ "trace_id": "t2" }), now);
+        assert!(state.pending_control.is_none());
+    }
+
+    #[test]
+    fn pane_handles_are_stable_and_reused_once_the_pane_is_gone() {
+        let mut state = State::default();
+        let now = OffsetDateTime::UNIX_EPOCH;
+        let assign = |state: &mut State, handle: u32, pane_id: &str, label: &str| {
+            state.apply(
+                "PaneHandleAssigned",
+                &json!({ "handle": handle, "pane_id": pane_id, "pane_label": label }),
+                now,
+            );
+        };
+        assert_eq!(state.free_handle(&[]), 1);
+        assign(&mut state, 1, "%1", "api:0.0");
+        assign(&mut state, 2, "%2", "web:0.1");
+        assert_eq!(state.pane_handle("%2"), Some((2, "web:0.1")));
+        assert_eq!(state.free_handle(&["%1", "%2"]), 3);
+        // %1 is gone, so #1 is free again.
+        assert_eq!(state.free_handle(&["%2", "%3"]), 1);
+        assign(&mut state, 1, "%3", "db:0.0");
+        assert_eq!(state.pane_handle("%1"), None);
+        // A relabelled pane keeps its handle under the new label.
+        assign(&mut state, 2, "%2", "web2:0.1");
+        assert_eq!(state.pane_handle("%2"), Some((2, "web2:0.1")));
+        assert_eq!(state.pane_handles.len(), 2);
+    }
+
+    #[test]
+    fn snapshot_round_trips_the_read_model() {
+        let mut state = State::default();
+        let now = OffsetDateTime::UNIX_EPOCH;
+        state.apply("TurnCompleted", &turn("%1", "fix it"), now);
+        state.apply(
+            "PaneHandleAssigned",
+            &json!({ "handle": 4, "pane_id": "%1", "pane_label": "api:0.0" }),
+            now,
+        );
+        state.apply(
+            "ChatDbCursorSaved",
+            &json!({ "inbound_rowid": 7, "self_rowid": 9 }),
+            now,
+        );
+        let json = serde_json::to_vec(&Snapshot {
+            format: SNAPSHOT_FORMAT,
+            next_version: 3,
+            last_event_id: None,
+            state: &state,
+        })
+        .unwrap();
+
+        let loaded: Snapshot<State> = serde_json::from_slice(&json).unwrap();
+        assert_eq!(loaded.next_version, 3);
+        let loaded = loaded.state;
+        assert_eq!(loaded.pane_handle("%1"), Some((4, "api:0.0")));
+        assert_eq!(loaded.last_turn("%1").unwrap().last_user_prompt, "fix it");
+        assert_eq!(loaded.chat_db_cursor.unwrap().self_rowid, 9);
+    }
+
+    #[test]
+    fn apply_tracks_manual_presence_override() {
+        let mut state = State::default();
+        let now = OffsetDateTime::UNIX_EPOCH;
+        state.apply("ManualPresenceSet", &json!({ "presence": "away" }), now);
+        assert_eq!(state.manual_presence, Some(Presence::Away));
+        state.apply("ManualPresenceSet", &json!({ "presence": null }), now);
+        assert_eq!(state.manual_presence, None);
+    }
+
+    #[test]
+    fn apply_ignores_unknown_and_malformed_events() {
+        let mut state = State::default();
+        let now = OffsetDateTime::UNIX_EPOCH;
+        state.apply("ReplyReceived", &json!({ "text": "hi" }), now);
+        state.apply("TurnCompleted", &json!({ "pane_id": "%1" }), now);
+        state.apply("AwayNotificationSent", &json!({ "pane_id": "%1" }), now);
+        assert!(state.last_turn("%1").is_none());
+        assert!(state.last_away_notification_source_agent.is_none());
+    }
+}
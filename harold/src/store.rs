@@ -1,9 +1,13 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
-use events::{ActorType, EventStore, ExpectedVersion, NewEvent, RotationPolicy};
+use events::{ActorType, EventEnvelope, EventStore, ExpectedVersion, NewEvent, RotationPolicy};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use time::OffsetDateTime;
+
+use crate::state;
 
 pub const STREAM_ID: &str = "harold.events";
 
@@ -19,6 +23,223 @@ pub struct TurnCompleted {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReplyReceived {
     pub text: String,
+    /// Token of the notification this answers, when the channel carries one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_token: Option<String>,
+}
+
+/// An away notification was sent for `pane_id`. Un-tagged replies route here;
+/// replies carrying `reply_token` route here whatever was sent since.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AwayNotificationSent {
+    pub pane_id: String,
+    pub pane_label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_token: Option<String>,
+    /// Further tokens for the same notification, e.g. the event id of a
+    /// Matrix question posted in its thread.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_reply_tokens: Vec<String>,
+}
+
+/// IMAP UID of the last email the listener processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmailCursorSaved {
+    pub uid: u32,
+}
+
+/// chat.db rowids the listener has fully processed. The latest one is where polling
+/// resumes after a restart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatDbCursorSaved {
+    pub inbound_rowid: i64,
+    pub self_rowid: i64,
+}
+
+/// An iMessage Harold sent, found in chat.db. The iMessage source drops this
+/// row instead of treating it as a reply from the user's other devices.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImessageSent {
+    pub rowid: i64,
+    pub guid: String,
+}
+
+/// Telegram `getUpdates` offset the listener has fully processed — where long
+/// polling resumes after a restart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TelegramCursorSaved {
+    pub offset: i64,
+}
+
+/// Matrix `/sync` batch token the listener has fully processed — where long
+/// polling resumes after a restart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatrixCursorSaved {
+    pub next_batch: String,
+}
+
+/// Whether the user is at the desk (TTS) or away (iMessage/Telegram).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Presence {
+    AtDesk,
+    Away,
+}
+
+/// Manual presence override set over gRPC or `harold --presence`. `None` returns
+/// to automatic detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManualPresenceSet {
+    pub presence: Option<Presence>,
+}
+
+/// The reply lane's first start. Replies before `from_version` were routed by
+/// the notification lane before the lanes were split, so the lane skips them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplyLaneStarted {
+    pub from_version: u64,
+}
+
+/// Why a `TurnCompleted` did not produce a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The pane's tmux session has an attached client and the user is at the desk.
+    SessionActive,
+    /// The pane is the active pane and the user is at the desk.
+    PaneActive,
+    /// Identical to the last message sent.
+    Duplicate,
+    /// The channel has no recipient configured.
+    NotConfigured,
+    /// Older than `notify.max_turn_age_secs` when the projector reached it.
+    Stale,
+}
+
+/// How a reply was matched to an agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteMethod {
+    ReplyToken,
+    ExactTag,
+    TagSubstring,
+    Semantic,
+    LastAwayNotification,
+    MyAgentFallback,
+    /// One of several panes named by `[all]`, `[a,b]` or a glob.
+    MultiTag,
+    /// A `#3` handle from an away notification.
+    Handle,
+}
+
+// Outcome events. `trace_id` is the id of the event that triggered the decision.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSent {
+    pub trace_id: String,
+    pub pane_id: String,
+    pub pane_label: String,
+    pub channel: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSkipped {
+    pub trace_id: String,
+    pub pane_id: String,
+    pub pane_label: String,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationFailed {
+    pub trace_id: String,
+    pub pane_id: String,
+    pub pane_label: String,
+    pub channel: String,
+    pub reason: String,
+}
+
+/// Stale turns folded into a single "while Harold was offline" notification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfflineDigestSent {
+    pub channel: String,
+    pub pane_labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplyRouted {
+    pub trace_id: String,
+    pub pane_id: String,
+    pub label: String,
+    pub method: RouteMethod,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplyRoutingFailed {
+    pub trace_id: String,
+    pub reason: String,
+    pub available_panes: Vec<String>,
+}
+
+/// A `!command` reply was answered instead of routed. `error` is what was
+/// sent back when it failed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplyCommandHandled {
+    pub trace_id: String,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// `pane_id` answers to `#handle` in away notifications and replies. A pane
+/// keeps its handle; a handle moves to another pane only once its pane is gone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaneHandleAssigned {
+    pub handle: u32,
+    pub pane_id: String,
+    pub pane_label: String,
+}
+
+/// A fixed key a reply command can send to a pane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlKey {
+    Stop,
+    Interrupt,
+    Enter,
+}
+
+/// A control key was asked for and awaits `!yes`. Only the latest request can
+/// be confirmed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlKeyRequested {
+    pub trace_id: String,
+    pub pane_id: String,
+    pub label: String,
+    pub key: ControlKey,
+}
+
+/// A confirmed control key was sent. `trace_id` is the confirming reply.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlKeySent {
+    pub trace_id: String,
+    pub pane_id: String,
+    pub label: String,
+    pub key: ControlKey,
+}
+
+/// The pending control key request was cancelled with `!no`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlKeyCancelled {
+    pub trace_id: String,
+}
+
+/// Text sent to an agent through the `SendToAgent` RPC.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentMessageSent {
+    pub pane_id: String,
+    pub pane_label: String,
+    pub text: String,
 }
 
 fn rotation_policy() -> RotationPolicy {
@@ -33,42 +254,196 @@ pub async fn open_store(path: &str) -> events::Result<Arc<EventStore>> {
     Ok(Arc::new(store))
 }
 
-pub async fn append_turn_completed(
+/// The version the next appended event will receive. Seeded by
+/// `state::rebuild`, then advanced by every append.
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(0);
+
+async fn append_event(
     store: &EventStore,
-    event: &TurnCompleted,
+    event_type: &str,
+    payload: serde_json::Value,
 ) -> events::Result<()> {
     store
         .append(
             STREAM_ID,
             ExpectedVersion::Any,
             vec![NewEvent {
-                r#type: "TurnCompleted".into(),
-                payload: json!(event),
+                r#type: event_type.into(),
+                payload: payload.clone(),
                 request_id: None,
                 actor_id: "system:harold".into(),
                 actor_type: ActorType::System,
             }],
         )
         .await?;
+    NEXT_VERSION.fetch_add(1, Ordering::SeqCst);
+    // Keep the in-memory read model in step with the stream.
+    state::apply(event_type, &payload, OffsetDateTime::now_utc());
     Ok(())
 }
 
+pub async fn append_turn_completed(
+    store: &EventStore,
+    event: &TurnCompleted,
+) -> events::Result<()> {
+    append_event(store, "TurnCompleted", json!(event)).await
+}
+
 pub async fn append_reply_received(
     store: &EventStore,
     event: &ReplyReceived,
 ) -> events::Result<()> {
-    store
-        .append(
-            STREAM_ID,
-            ExpectedVersion::Any,
-            vec![NewEvent {
-                r#type: "ReplyReceived".into(),
-                payload: json!(event),
-                request_id: None,
-                actor_id: "system:harold".into(),
-                actor_type: ActorType::System,
-            }],
-        )
-        .await?;
-    Ok(())
+    append_event(store, "ReplyReceived", json!(event)).await
+}
+
+pub async fn append_away_notification_sent(
+    store: &EventStore,
+    event: &AwayNotificationSent,
+) -> events::Result<()> {
+    append_event(store, "AwayNotificationSent", json!(event)).await
+}
+
+pub async fn append_chat_db_cursor_saved(
+    store: &EventStore,
+    event: &ChatDbCursorSaved,
+) -> events::Result<()> {
+    append_event(store, "ChatDbCursorSaved", json!(event)).await
+}
+
+pub async fn append_imessage_sent(store: &EventStore, event: &ImessageSent) -> events::Result<()> {
+    append_event(store, "ImessageSent", json!(event)).await
+}
+
+pub async fn append_email_cursor_saved(
+    store: &EventStore,
+    event: &EmailCursorSaved,
+) -> events::Result<()> {
+    append_event(store, "EmailCursorSaved", json!(event)).await
+}
+
+pub async fn append_telegram_cursor_saved(
+    store: &EventStore,
+    event: &TelegramCursorSaved,
+) -> events::Result<()> {
+    append_event(store, "TelegramCursorSaved", json!(event)).await
+}
+
+pub async fn append_matrix_cursor_saved(
+    store: &EventStore,
+    event: &MatrixCursorSaved,
+) -> events::Result<()> {
+    append_event(store, "MatrixCursorSaved", json!(event)).await
+}
+
+pub async fn append_reply_lane_started(
+    store: &EventStore,
+    event: &ReplyLaneStarted,
+) -> events::Result<()> {
+    append_event(store, "ReplyLaneStarted", json!(event)).await
+}
+
+pub async fn append_manual_presence_set(
+    store: &EventStore,
+    event: &ManualPresenceSet,
+) -> events::Result<()> {
+    append_event(store, "ManualPresenceSet", json!(event)).await
+}
+
+pub async fn append_agent_message_sent(
+    store: &EventStore,
+    event: &AgentMessageSent,
+) -> events::Result<()> {
+    append_event(store, "AgentMessageSent", json!(event)).await
+}
+
+pub async fn append_notification_sent(
+    store: &EventStore,
+    event: &NotificationSent,
+) -> events::Result<()> {
+    append_event(store, "NotificationSent", json!(event)).await
+}
+
+pub async fn append_notification_skipped(
+    store: &EventStore,
+    event: &NotificationSkipped,
+) -> events::Result<()> {
+    append_event(store, "NotificationSkipped", json!(event)).await
+}
+
+pub async fn append_notification_failed(
+    store: &EventStore,
+    event: &NotificationFailed,
+) -> events::Result<()> {
+    append_event(store, "NotificationFailed", json!(event)).await
+}
+
+pub async fn append_offline_digest_sent(
+    store: &EventStore,
+    event: &OfflineDigestSent,
+) -> events::Result<()> {
+    append_event(store, "OfflineDigestSent", json!(event)).await
+}
+
+pub async fn append_reply_command_handled(
+    store: &EventStore,
+    event: &ReplyCommandHandled,
+) -> events::Result<()> {
+    append_event(store, "ReplyCommandHandled", json!(event)).await
+}
+
+pub async fn append_pane_handle_assigned(
+    store: &EventStore,
+    event: &PaneHandleAssigned,
+) -> events::Result<()> {
+    append_event(store, "PaneHandleAssigned", json!(event)).await
+}
+
+pub async fn append_control_key_requested(
+    store: &EventStore,
+    event: &ControlKeyRequested,
+) -> events::Result<()> {
+    append_event(store, "ControlKeyRequested", json!(event)).await
+}
+
+pub async fn append_control_key_sent(
+    store: &EventStore,
+    event: &ControlKeySent,
+) -> events::Result<()> {
+    append_event(store, "ControlKeySent", json!(event)).await
+}
+
+pub async fn append_control_key_cancelled(
+    store: &EventStore,
+    event: &ControlKeyCancelled,
+) -> events::Result<()> {
+    append_event(store, "ControlKeyCancelled", json!(event)).await
+}
+
+pub async fn append_reply_routed(store: &EventStore, event: &ReplyRouted) -> events::Result<()> {
+    append_event(store, "ReplyRouted", json!(event)).await
+}
+
+pub async fn append_reply_routing_failed(
+    store: &EventStore,
+    event: &ReplyRoutingFailed,
+) -> events::Result<()> {
+    append_event(store, "ReplyRoutingFailed", json!(event)).await
+}
+
+/// Read up to `max_count` events from the harold stream, starting at `from_version`.
+pub async fn read_events(
+    store: &EventStore,
+    from_version: u64,
+    max_count: usize,
+) -> events::Result<Vec<EventEnvelope>> {
+    store.read_stream(STREAM_ID, from_version, max_count).await
+}
+
+/// The version the next appended event will receive.
+pub fn next_version() -> u64 {
+    NEXT_VERSION.load(Ordering::SeqCst)
+}
+
+pub(crate) fn set_next_version(version: u64) {
+    NEXT_VERSION.store(version, Ordering::SeqCst);
 }
@@ -0,0 +1,57 @@
+use tokio::process::Command;
+use tracing::warn;
+
+use super::{Summarizer, SummaryRequest};
+use crate::proc::{Tool, run_blocking};
+use crate::settings::get_settings;
+use crate::util::ai_cli_env;
+
+/// The AI CLI (`ai.cli_path`) in one-shot print mode.
+pub(super) struct AiCli;
+
+impl Summarizer for AiCli {
+    fn name(&self) -> &'static str {
+        "cli"
+    }
+
+    fn summarize(&self, req: &SummaryRequest) -> Option<String> {
+        let cfg = get_settings();
+        let cli = cfg.ai.cli_path.as_deref()?;
+
+        let prompt = if req.system_prompt.is_empty() {
+            req.prompt.to_string()
+        } else {
+            format!("{}\n\n{}", req.system_prompt, req.prompt)
+        };
+        let mut cmd = Command::new(cli);
+        cmd.args([
+            "-p",
+            &prompt,
+            "--model",
+            &cfg.ai.cli_model,
+            "--max-turns",
+            "1",
+            "--settings",
+            r#"{"disableAllHooks":true}"#,
+        ])
+        .env_remove("CLAUDECODE")
+        .envs(ai_cli_env());
+        let out = run_blocking(cmd, Tool::AiCli).ok()?;
+
+        if !out.status.success() {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            warn!(
+                status = %out.status,
+                stderr = %stderr.chars().take(200).collect::<String>(),
+                "summarizer: AI CLI failed"
+            );
+            return None;
+        }
+        let summary = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        if summary.is_empty() {
+            None
+        } else {
+            Some(summary)
+        }
+    }
+}
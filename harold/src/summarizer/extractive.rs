@@ -0,0 +1,375 @@
+use super::{Summarizer, SummaryRequest};
+
+/// Offline heuristic — no model, always available. Deterministic, so it is also
+/// the last-resort fallback when every configured backend declines.
+pub(super) struct Extractive;
+
+impl Summarizer for Extractive {
+    fn name(&self) -> &'static str {
+        "extractive"
+    }
+
+    fn summarize(&self, req: &SummaryRequest) -> Option<String> {
+        let summary = extract(req.source, req.max_chars);
+        if summary.is_empty() {
+            None
+        } else {
+            Some(summary)
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Markdown stripping
+// ---------------------------------------------------------------------------
+
+/// Prose lines of a markdown transcript. Fenced code blocks, tables and
+/// headings are dropped; quotes, list markers, emphasis and link targets are
+/// stripped. Each returned line is a paragraph or list item.
+fn plain_lines(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut in_fence = false;
+    for raw in text.lines() {
+        let line = raw.trim();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence || line.starts_with('|') || line.starts_with('#') {
+            continue;
+        }
+        let line = line.trim_start_matches('>').trim();
+        let line = strip_inline(strip_list_marker(line));
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+fn strip_list_marker(line: &str) -> &str {
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(marker) {
+            return rest.trim_start();
+        }
+    }
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    if digits > 0 && (line[digits..].starts_with(". ") || line[digits..].starts_with(") ")) {
+        return line[digits + 2..].trim_start();
+    }
+    line
+}
+
+/// Remove inline markup: backticks and emphasis markers go, link text stays.
+fn strip_inline(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '`' | '*' => rest = &rest[1..],
+            '!' if rest[1..].starts_with('[') => rest = &rest[1..],
+            '[' => match link_text(rest) {
+                Some((text, after)) => {
+                    out.push_str(text);
+                    rest = after;
+                }
+                None => {
+                    out.push('[');
+                    rest = &rest[1..];
+                }
+            },
+            _ => {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// For `[text](url)…`, returns `text` and the remainder after the link.
+fn link_text(s: &str) -> Option<(&str, &str)> {
+    let close = s.find("](")?;
+    let end = close + s[close..].find(')')?;
+    Some((&s[1..close], &s[end + 1..]))
+}
+
+// ---------------------------------------------------------------------------
+// Sentence scoring
+// ---------------------------------------------------------------------------
+
+/// Openers of narration about what the agent is about to do — rarely the outcome.
+const PREAMBLE: &[&str] = &[
+    "i'll ",
+    "i will ",
+    "i'm going to ",
+    "i need to ",
+    "let me ",
+    "let's ",
+    "first, ",
+    "next, ",
+    "now i",
+    "okay",
+    "ok,",
+    "sure",
+    "great",
+    "looking at ",
+];
+
+/// Word stems that mark a result or conclusion.
+const CONCLUSION: &[&str] = &[
+    "fixed",
+    "fixes",
+    "added",
+    "implement",
+    "updated",
+    "created",
+    "removed",
+    "renamed",
+    "refactor",
+    "resolved",
+    "pass",
+    "succeed",
+    "success",
+    "complete",
+    "finished",
+    "works",
+    "result",
+    "summary",
+    "ready",
+    "merged",
+    "committed",
+    "pushed",
+    "deployed",
+    "applied",
+    "failed",
+    "failing",
+    "error",
+    "blocked",
+    "done",
+];
+
+fn split_sentences(line: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    for (i, c) in line.char_indices() {
+        // Terminators are ASCII, so i + 1 is a char boundary.
+        if matches!(c, '.' | '!' | '?') && line[i + 1..].chars().next().is_none_or(|n| n == ' ') {
+            let s = line[start..=i].trim();
+            if !s.is_empty() {
+                sentences.push(s);
+            }
+            start = i + 1;
+        }
+    }
+    let rest = line[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+    sentences
+}
+
+/// Sentences scoring below this are left out even if there is room for them.
+const MIN_SCORE: f64 = 1.0;
+
+/// Higher is more summary-worthy. `position` is 0.0 for the first sentence and
+/// 1.0 for the last — agents put their conclusions at the end.
+fn score(sentence: &str, position: f64) -> f64 {
+    let lower = sentence.to_lowercase();
+    let mut score = 1.5 * position;
+    if PREAMBLE.iter().any(|p| lower.starts_with(p)) {
+        score -= 3.0;
+    }
+    let cues = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| CONCLUSION.iter().any(|stem| w.starts_with(stem)))
+        .count();
+    if cues > 0 {
+        score += 2.0 + 0.5 * (cues - 1).min(2) as f64;
+    }
+    if sentence.chars().any(|c| c.is_ascii_digit()) {
+        score += 0.5;
+    }
+    // Identifiers (`mod::fn`, `snake_case`) read as jargon on a phone.
+    if sentence
+        .split_whitespace()
+        .any(|w| w.contains("::") || w.trim_matches('_').contains('_'))
+    {
+        score -= 2.0;
+    }
+    let words = sentence.split_whitespace().count();
+    if !(3..=45).contains(&words) {
+        score -= 1.0;
+    }
+    score
+}
+
+/// Cut `s` to `max_chars` at a word boundary, marking the cut with an ellipsis.
+fn clip(s: &str, max_chars: usize) -> String {
+    if s.chars().count() <= max_chars {
+        return s.to_string();
+    }
+    let cut: String = s.chars().take(max_chars.saturating_sub(1)).collect();
+    let cut = cut.rfind(' ').map_or(cut.as_str(), |i| &cut[..i]);
+    format!("{}…", cut.trim_end())
+}
+
+/// List items and headings-turned-sentences often lack a full stop.
+fn terminated(sentence: &str) -> String {
+    if sentence.ends_with(['.', '!', '?', ':']) {
+        sentence.to_string()
+    } else {
+        format!("{sentence}.")
+    }
+}
+
+// ---------------------------------------------------------------------------
+// extract
+// ---------------------------------------------------------------------------
+
+/// Summarise `text` in at most `max_chars` characters without a model.
+///
+/// Markdown and code are stripped, sentences are scored (results and
+/// conclusions up, preamble down, later sentences up) and the best ones that
+/// fit are kept in their original order. A trailing question is always kept
+/// last, so `split_body` can still send it separately.
+pub fn extract(text: &str, max_chars: usize) -> String {
+    let lines = plain_lines(text);
+    let mut sentences: Vec<&str> = lines.iter().flat_map(|l| split_sentences(l)).collect();
+    let Some(last) = sentences.last() else {
+        return String::new();
+    };
+
+    let question = last.ends_with('?').then_some(*last);
+    let mut budget = max_chars;
+    if let Some(q) = question {
+        sentences.pop();
+        let q_len = q.chars().count();
+        if q_len >= max_chars {
+            return clip(q, max_chars);
+        }
+        budget -= q_len + 1;
+    }
+
+    let n = sentences.len();
+    let scores: Vec<f64> = sentences
+        .iter()
+        .enumerate()
+        .map(|(i, s)| score(s, i as f64 / n.saturating_sub(1).max(1) as f64))
+        .collect();
+    let mut ranked: Vec<usize> = (0..n).collect();
+    // Best first; on a tie the later sentence wins.
+    ranked.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]).then(b.cmp(&a)));
+
+    let mut chosen = Vec::new();
+    let mut used = 0;
+    for &i in &ranked {
+        if scores[i] < MIN_SCORE {
+            break;
+        }
+        let len = sentences[i].chars().count() + usize::from(!chosen.is_empty());
+        if used + len <= budget {
+            chosen.push(i);
+            used += len;
+        }
+    }
+    chosen.sort_unstable();
+
+    let mut parts: Vec<String> = chosen.iter().map(|&i| terminated(sentences[i])).collect();
+    // Nothing fits whole: cut the best sentence, unless the question can stand alone.
+    if parts.is_empty()
+        && question.is_none()
+        && let Some(&best) = ranked.first()
+    {
+        parts.push(clip(&terminated(sentences[best]), budget));
+    }
+    parts.extend(question.map(String::from));
+    parts.join(" ")
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREAMBLE_THEN_RESULT: &str = include_str!("fixtures/preamble_then_result.md");
+    const TRAILING_QUESTION: &str = include_str!("fixtures/trailing_question.md");
+    const LONG_TRANSCRIPT: &str = include_str!("fixtures/long_transcript.md");
+
+    #[test]
+    fn plain_lines_strips_markdown_and_code() {
+        let lines = plain_lines(PREAMBLE_THEN_RESULT);
+        assert!(lines.iter().all(|l| !l.contains("fn is_expired")));
+        assert!(!lines.contains(&"Summary".to_string()));
+        assert!(
+            lines.contains(
+                &"Fixed the expiry comparison in auth/token.rs to convert both sides to seconds"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            plain_lines("1. See [the docs](https://x.test/a) and **this**"),
+            vec!["See the docs and this"]
+        );
+    }
+
+    #[test]
+    fn split_sentences_ignores_dots_inside_words() {
+        assert_eq!(
+            split_sentences("Bumped to 1.2.3 in Cargo.toml. Done! Ship it?"),
+            vec!["Bumped to 1.2.3 in Cargo.toml.", "Done!", "Ship it?"]
+        );
+    }
+
+    #[test]
+    fn extract_prefers_results_over_preamble() {
+        let summary = extract(PREAMBLE_THEN_RESULT, 280);
+        assert!(summary.chars().count() <= 280);
+        assert!(summary.contains("All 42 tests pass."), "{summary}");
+        assert!(summary.contains("Fixed the expiry comparison"), "{summary}");
+        assert!(!summary.contains("I'll start"), "{summary}");
+        assert!(!summary.contains('`'), "{summary}");
+    }
+
+    #[test]
+    fn extract_keeps_trailing_question_last() {
+        let summary = extract(TRAILING_QUESTION, 280);
+        assert!(summary.chars().count() <= 280);
+        assert!(
+            summary.ends_with("Should I run the same migrations against production now?"),
+            "{summary}"
+        );
+        assert!(
+            summary.contains("applied to staging successfully"),
+            "{summary}"
+        );
+        assert!(!summary.contains("0007"), "{summary}");
+    }
+
+    #[test]
+    fn extract_respects_budget_and_is_deterministic() {
+        for budget in [60, 140, 280] {
+            let summary = extract(LONG_TRANSCRIPT, budget);
+            assert!(summary.chars().count() <= budget, "{budget}: {summary}");
+            assert!(!summary.is_empty());
+            assert_eq!(summary, extract(LONG_TRANSCRIPT, budget));
+        }
+        let summary = extract(LONG_TRANSCRIPT, 280);
+        assert!(summary.contains("no failures"), "{summary}");
+        assert!(!summary.starts_with("I'll"), "{summary}");
+    }
+
+    #[test]
+    fn extract_clips_when_nothing_fits() {
+        let summary = extract(
+            "Refactored the whole settings loader to use layered sources.",
+            20,
+        );
+        assert_eq!(summary, "Refactored the…");
+        assert_eq!(extract("", 280), "");
+        assert_eq!(extract("```\nonly code\n```", 280), "");
+    }
+}
@@ -0,0 +1,168 @@
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+
+use serde::Deserialize;
+use serde_json::json;
+use tokio::process::Command;
+use tracing::warn;
+
+use super::{Summarizer, SummaryRequest, regex_strip_think};
+use crate::proc::{Tool, run_blocking_with_input};
+use crate::settings::get_settings;
+use crate::util::curl_config;
+
+/// An OpenAI-compatible `/v1/chat/completions` endpoint — Ollama, llama.cpp
+/// server, LM Studio, vLLM. Requests go through `curl`, like every other tool.
+pub(super) struct HttpModel;
+
+#[derive(Deserialize)]
+struct ChatCompletion {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    content: String,
+}
+
+fn parse_chat_completion(body: &[u8]) -> Option<String> {
+    let completion: ChatCompletion = serde_json::from_slice(body).ok()?;
+    let content = &completion.choices.first()?.message.content;
+    let text = regex_strip_think(content.trim().trim_matches('"'));
+    if text.is_empty() { None } else { Some(text) }
+}
+
+/// POST `body` to `url` and return the response body. The key goes in the
+/// curl config on stdin. The body holds the whole assistant message, too long
+/// for argv or a config line, so it is sent from a private file.
+fn post(url: &str, api_key: Option<&str>, body: &str) -> Option<Vec<u8>> {
+    let path = std::env::temp_dir().join(format!("harold-chat-{}.json", uuid::Uuid::new_v4()));
+    let written = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)
+        .and_then(|mut file| file.write_all(body.as_bytes()));
+    let response = match written {
+        Ok(()) => request(url, api_key, &format!("@{}", path.display())),
+        Err(e) => {
+            warn!(error = %e, "summarizer: cannot stage HTTP model request");
+            None
+        }
+    };
+    let _ = std::fs::remove_file(&path);
+    response
+}
+
+fn request(url: &str, api_key: Option<&str>, data: &str) -> Option<Vec<u8>> {
+    let mut cmd = Command::new("curl");
+    cmd.args([
+        "--silent",
+        "--show-error",
+        "--fail",
+        "-K",
+        "-",
+        "-X",
+        "POST",
+        "-H",
+        "Content-Type: application/json",
+        url,
+    ]);
+    let auth = api_key.map(|key| format!("Authorization: Bearer {key}"));
+    let mut options = vec![("data-binary", data)];
+    if let Some(auth) = &auth {
+        options.push(("header", auth));
+    }
+    let out = run_blocking_with_input(cmd, Tool::Http, curl_config(&options)).ok()?;
+
+    if !out.status.success() {
+        warn!(
+            status = %out.status,
+            stderr = %String::from_utf8_lossy(&out.stderr).trim(),
+            "summarizer: HTTP model request failed"
+        );
+        return None;
+    }
+    Some(out.stdout)
+}
+
+impl Summarizer for HttpModel {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn summarize(&self, req: &SummaryRequest) -> Option<String> {
+        let cfg = get_settings();
+        let http = cfg.ai.http.as_ref()?;
+
+        let body = json!({
+            "model": http.model,
+            "messages": [
+                { "role": "system", "content": req.system_prompt },
+                { "role": "user", "content": req.prompt },
+            ],
+            "max_tokens": req.max_tokens,
+            "stream": false,
+        });
+        let response = post(&http.url, http.api_key.as_deref(), &body.to_string())?;
+        let summary = parse_chat_completion(&response);
+        if summary.is_none() {
+            warn!("summarizer: HTTP model returned no usable completion");
+        }
+        summary.map(|s| s.chars().take(req.max_chars).collect())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::stand_in;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn post_sends_long_body_and_key_off_argv() {
+        crate::settings::init_settings_for_test();
+        let (base, rx) = stand_in::serve(vec![(200, r#"{"choices":[]}"#)]);
+        // Far past the 128 KiB a single argument may hold.
+        let prompt = "quote \" \\ and\nnewline ".repeat(10_000);
+        let body = json!({ "messages": [{ "role": "user", "content": prompt }] }).to_string();
+
+        let response = tokio::task::spawn_blocking({
+            let body = body.clone();
+            move || {
+                post(
+                    &format!("{base}/v1/chat/completions"),
+                    Some("sk-test"),
+                    &body,
+                )
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(response.as_deref(), Some(&br#"{"choices":[]}"#[..]));
+
+        let req = rx.recv().unwrap();
+        assert_eq!(req.path, "/v1/chat/completions");
+        assert_eq!(req.header("Authorization"), Some("Bearer sk-test"));
+        assert_eq!(req.body, body);
+    }
+
+    #[test]
+    fn parse_chat_completion_takes_first_choice() {
+        let body = br#"{"choices":[{"message":{"role":"assistant","content":" <think>x</think>Fixed the login bug "}}]}"#;
+        assert_eq!(
+            parse_chat_completion(body).as_deref(),
+            Some("Fixed the login bug")
+        );
+        assert_eq!(parse_chat_completion(br#"{"choices":[]}"#), None);
+        assert_eq!(parse_chat_completion(b"not json"), None);
+    }
+}
@@ -0,0 +1,81 @@
+use tokio::process::Command;
+
+use super::{Summarizer, SummaryRequest, regex_strip_think};
+use crate::proc::{Tool, run_blocking};
+use crate::settings::get_settings;
+
+/// A local MLX model run through `uv run mlx_lm.generate` (Apple silicon only).
+pub(super) struct Mlx;
+
+impl Summarizer for Mlx {
+    fn name(&self) -> &'static str {
+        "mlx"
+    }
+
+    fn summarize(&self, req: &SummaryRequest) -> Option<String> {
+        let cfg = get_settings();
+        let model = cfg.ai.local_model.as_deref()?;
+        let model_dir = cfg.ai.local_model_dir.as_deref()?;
+
+        let mut cmd = Command::new("uv");
+        cmd.args([
+            "run",
+            "mlx_lm.generate",
+            "--model",
+            model,
+            "--system-prompt",
+            req.system_prompt,
+            "--prompt",
+            req.prompt,
+            "--max-tokens",
+            &req.max_tokens.to_string(),
+        ])
+        .current_dir(model_dir);
+        let out = run_blocking(cmd, Tool::LocalModel).ok()?;
+
+        if !out.status.success() {
+            return None;
+        }
+
+        let output = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        if output.is_empty() {
+            return None;
+        }
+        parse_generate_output(&output, req.max_chars)
+    }
+}
+
+fn parse_generate_output(output: &str, max_chars: usize) -> Option<String> {
+    // Extract content between ========== markers (mlx_lm output format)
+    if output.contains("==========") {
+        let mut in_content = false;
+        let mut lines: Vec<&str> = Vec::new();
+        for line in output.lines() {
+            if line.trim() == "==========" {
+                if in_content {
+                    break;
+                }
+                in_content = true;
+            } else if in_content {
+                lines.push(line.trim());
+            }
+        }
+        let text = lines
+            .join(" ")
+            .trim()
+            .trim_matches('"')
+            .trim_matches('\'')
+            .to_string();
+        // Strip <think>...</think> blocks (reasoning models)
+        let text = regex_strip_think(&text);
+        if text.is_empty() { None } else { Some(text) }
+    } else {
+        let text = output.trim_matches('"').trim_matches('\'');
+        let text = regex_strip_think(text);
+        if text.is_empty() {
+            None
+        } else {
+            Some(text.chars().take(max_chars).collect())
+        }
+    }
+}
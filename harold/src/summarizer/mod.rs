@@ -0,0 +1,132 @@
+mod cli;
+mod extractive;
+mod http;
+mod mlx;
+
+use tracing::{debug, info};
+
+use crate::settings::SummarizerBackend;
+
+pub use extractive::extract;
+
+// ---------------------------------------------------------------------------
+// Summarizer — one trait, several backends, a fallback chain per call site
+// ---------------------------------------------------------------------------
+
+/// Everything a backend may need. Model backends use the prompts; the extractive
+/// backend works directly on `source`.
+pub struct SummaryRequest<'a> {
+    pub system_prompt: &'a str,
+    pub prompt: &'a str,
+    /// The text being summarised (assistant reply, user prompt, ...).
+    pub source: &'a str,
+    pub max_chars: usize,
+    pub max_tokens: u32,
+}
+
+pub trait Summarizer {
+    fn name(&self) -> &'static str;
+
+    /// `None` if the backend is not configured, failed or produced nothing.
+    fn summarize(&self, req: &SummaryRequest) -> Option<String>;
+}
+
+fn backend(kind: SummarizerBackend) -> Box<dyn Summarizer> {
+    match kind {
+        SummarizerBackend::Cli => Box::new(cli::AiCli),
+        SummarizerBackend::Mlx => Box::new(mlx::Mlx),
+        SummarizerBackend::Http => Box::new(http::HttpModel),
+        SummarizerBackend::Extractive => Box::new(extractive::Extractive),
+    }
+}
+
+/// Try each backend in `chain` in order; the first summary wins.
+pub fn summarize(chain: &[SummarizerBackend], req: &SummaryRequest) -> Option<String> {
+    let backends: Vec<Box<dyn Summarizer>> = chain.iter().copied().map(backend).collect();
+    first_summary(backends.iter().map(|b| b.as_ref()), req)
+}
+
+fn first_summary<'a>(
+    backends: impl IntoIterator<Item = &'a dyn Summarizer>,
+    req: &SummaryRequest,
+) -> Option<String> {
+    for b in backends {
+        match b.summarize(req) {
+            Some(summary) => {
+                info!(backend = b.name(), "summary produced");
+                return Some(summary);
+            }
+            None => debug!(backend = b.name(), "no summary, trying next backend"),
+        }
+    }
+    None
+}
+
+pub(crate) fn regex_strip_think(text: &str) -> String {
+    // Remove <think>...</think> blocks emitted by reasoning models like Qwen3.
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<think>") {
+        result.push_str(&rest[..start]);
+        if let Some(end) = rest.find("</think>") {
+            rest = rest[end + "</think>".len()..].trim_start();
+        } else {
+            rest = "";
+            break;
+        }
+    }
+    result.push_str(rest);
+    result.trim().to_string()
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str, Option<&'static str>);
+
+    impl Summarizer for Fixed {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn summarize(&self, _req: &SummaryRequest) -> Option<String> {
+            self.1.map(String::from)
+        }
+    }
+
+    fn req() -> SummaryRequest<'static> {
+        SummaryRequest {
+            system_prompt: "",
+            prompt: "",
+            source: "",
+            max_chars: 280,
+            max_tokens: 20,
+        }
+    }
+
+    #[test]
+    fn first_summary_falls_through_to_next_backend() {
+        let down = Fixed("down", None);
+        let up = Fixed("up", Some("done"));
+        let never = Fixed("never", Some("unreached"));
+        let chain: [&dyn Summarizer; 3] = [&down, &up, &never];
+        assert_eq!(first_summary(chain, &req()).as_deref(), Some("done"));
+
+        let empty: [&dyn Summarizer; 1] = [&down];
+        assert_eq!(first_summary(empty, &req()), None);
+    }
+
+    #[test]
+    fn regex_strip_think_removes_reasoning_blocks() {
+        assert_eq!(
+            regex_strip_think("<think>hmm</think> Fixed login"),
+            "Fixed login"
+        );
+        assert_eq!(regex_strip_think("Done <think>unterminated"), "Done");
+    }
+}
@@ -1,7 +1,11 @@
-use std::process::Command;
+use tokio::process::Command;
+
+use crate::proc::{Tool, run_blocking};
 
 fn query(args: &[&str]) -> Option<String> {
-    let out = Command::new("tmux").args(args).output().ok()?;
+    let mut cmd = Command::new("tmux");
+    cmd.args(args);
+    let out = run_blocking(cmd, Tool::Tmux).ok()?;
     let s = String::from_utf8_lossy(&out.stdout).trim().to_string();
     if s.is_empty() { None } else { Some(s) }
 }
@@ -19,6 +23,20 @@ pub fn is_session_attached(pane_id: &str) -> bool {
         .is_some_and(|v| v != "0")
 }
 
+/// Bring `pane_id` to the front: switch the most recent client to its session,
+/// then select its window and the pane itself.
+pub fn select_pane(pane_id: &str) {
+    for args in [
+        ["switch-client", "-t", pane_id],
+        ["select-window", "-t", pane_id],
+        ["select-pane", "-t", pane_id],
+    ] {
+        let mut cmd = Command::new("tmux");
+        cmd.args(args);
+        let _ = run_blocking(cmd, Tool::Tmux);
+    }
+}
+
 /// Returns the active pane of the session that `pane_id` belongs to,
 /// only if the session has an attached client (someone is looking at it).
 pub fn active_pane_in_session(pane_id: &str) -> Option<String> {
@@ -19,3 +19,29 @@ pub(crate) fn sanitise_for_applescript(text: &str) -> String {
         .filter(|c| *c != '\n' && *c != '\r' && *c != '¬' && !c.is_control())
         .collect()
 }
+
+/// Milliseconds since the Unix epoch, as carried in gRPC messages.
+pub(crate) fn unix_ms(t: time::OffsetDateTime) -> i64 {
+    (t.unix_timestamp_nanos() / 1_000_000) as i64
+}
+
+/// A curl config for `curl -K -`, setting each option to its value. Tokens and
+/// passwords go here, on stdin, rather than on argv where `ps` shows them.
+pub(crate) fn curl_config(options: &[(&str, &str)]) -> Vec<u8> {
+    let mut config = String::new();
+    for (option, value) in options {
+        let mut quoted = String::with_capacity(value.len());
+        for c in value.chars() {
+            match c {
+                '\\' => quoted.push_str("\\\\"),
+                '"' => quoted.push_str("\\\""),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\t' => quoted.push_str("\\t"),
+                c => quoted.push(c),
+            }
+        }
+        config.push_str(&format!("{option} = \"{quoted}\"\n"));
+    }
+    config.into_bytes()
+}